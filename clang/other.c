@@ -2,6 +2,7 @@
 // Created by Yuta Hinokuma on 2023/01/28.
 //
 
+#include <stdarg.h>
 #include <stdio.h>
 #include <stdlib.h>
 
@@ -24,3 +25,113 @@ int alloc4(int **p, int a, int b, int c, int d) {
     (*p)[2] = c;
     (*p)[3] = d;
 }
+
+// 構造体のレイアウトが cc と一致することを確かめる
+struct mixed {
+    char c;
+    long l;
+    short s;
+    int i;
+    char tail[3];
+};
+
+long mixed_sum(struct mixed *m) {
+    return m->c + m->l + m->s + m->i + m->tail[2];
+}
+
+void mixed_fill(struct mixed *m) {
+    m->c = 1;
+    m->l = 20;
+    m->s = 300;
+    m->i = 4000;
+    m->tail[2] = 5;
+}
+
+// 構造体の戻り値の受け渡しが cc と一致することを確かめる
+struct small {
+    int a;
+    char b;
+};
+
+struct pair {
+    long a;
+    long b;
+};
+
+struct big {
+    long a;
+    long b;
+    long c;
+};
+
+struct small make_small(int a, char b) {
+    struct small s = {a, b};
+    return s;
+}
+
+struct pair make_pair(long a, long b) {
+    struct pair p = {a, b};
+    return p;
+}
+
+struct big make_big(long a, long b, long c) {
+    struct big v = {a, b, c};
+    return v;
+}
+
+// 構造体の値渡しが cc と一致することを確かめる
+int small_sum(struct small s) {
+    return s.a + s.b;
+}
+
+long pair_diff(struct pair p) {
+    return p.a - p.b;
+}
+
+long big_digits(struct big v) {
+    return v.a * 100 + v.b * 10 + v.c;
+}
+
+// p はレジスタが 1 つしか残っていないのでスタックで渡り, f は残りのレジスタで渡る
+long struct_args7(int a, int b, int c, int d, int e, struct pair p, int f, struct small s, struct big v) {
+    int aligned = ((unsigned long)__builtin_frame_address(0) & 15) == 0;
+    return aligned && a + b + c + d + e == 15 && f == 6 ? p.a * 10 + p.b + s.a * s.b + v.a + v.b + v.c : -1;
+}
+
+char *skip(char *s, int n) {
+    return s + n;
+}
+
+long widen(int x) {
+    return x;
+}
+
+// 7 個目以降の引数がスタックで渡ることを確かめる
+long digits8(int a, int b, int c, int d, int e, int f, char g, long h) {
+    return ((((((a * 10L + b) * 10 + c) * 10 + d) * 10 + e) * 10 + f) * 10 + g) * 10 + h;
+}
+
+struct big make_big7(long a, long b, long c, long d, long e, long f, long g) {
+    struct big v = {a + b, c + d, e + f + g};
+    return v;
+}
+
+// call の直前に rsp が 16 バイト境界に揃っていれば, フレームポインタも 16 の倍数になる
+int stack_aligned(void) {
+    return ((unsigned long)__builtin_frame_address(0) & 15) == 0;
+}
+
+int stack_aligned7(int a, int b, int c, int d, int e, int f, int g) {
+    int aligned = ((unsigned long)__builtin_frame_address(0) & 15) == 0;
+    return aligned && a + b + c + d + e + f == 21 && g == 7;
+}
+
+int sum_ints(int n, ...) {
+    va_list ap;
+    va_start(ap, n);
+    int sum = 0;
+    for (int i = 0; i < n; i++)
+        sum += va_arg(ap, int);
+    va_end(ap);
+    return sum;
+}
@@ -0,0 +1,179 @@
+use crate::generator::GenerateError;
+use crate::parser::{ParseError, ParseWarning};
+use crate::source::{SourceFile, Span};
+use crate::tokenizer::TokenizeError;
+use std::fmt::{Display, Formatter, Write};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    /// コンパイルは続けられる問題
+    Warning,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// コンパイルエラーや警告を利用者に見せるための形式
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    severity: Severity,
+    message: String,
+    span: Option<Span>,
+}
+
+impl Diagnostic {
+    pub fn new(message: String, span: Option<Span>) -> Self {
+        Self {
+            severity: Severity::Error,
+            message,
+            span,
+        }
+    }
+
+    pub fn warning(message: String, span: Option<Span>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            message,
+            span,
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn span(&self) -> Option<Span> {
+        self.span
+    }
+
+    /// gcc 風に `file:line:column: error: message` と該当行, `^` を並べる. 警告は `warning:`
+    pub fn render(&self, file: &SourceFile) -> String {
+        let mut s = String::new();
+        match &self.span {
+            Some(span) => {
+                let source_line = file.line(span.line());
+                let _ = writeln!(
+                    s,
+                    "{}:{}:{}: {}: {}",
+                    file.name(),
+                    span.line(),
+                    span.column(),
+                    self.severity,
+                    self.message
+                );
+                let _ = writeln!(s, "{}", source_line);
+                // タブはそのまま残し, 全角文字は空白 2 つ分にして ^ の位置をずらさないようにする
+                let padding: String = source_line
+                    .chars()
+                    .take(span.column() - 1)
+                    .map(|c| match c {
+                        '\t' => "\t",
+                        c if is_wide(c) => "  ",
+                        _ => " ",
+                    })
+                    .collect();
+                let _ = writeln!(s, "{}^", padding);
+            }
+            None => {
+                let _ = writeln!(s, "{}: {}: {}", file.name(), self.severity, self.message);
+            }
+        }
+        s
+    }
+}
+
+/// 端末で 2 桁分の幅を取る文字 (East Asian Width が W または F) か
+fn is_wide(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x1100..=0x115F
+            | 0x2E80..=0x303E
+            | 0x3041..=0x33FF
+            | 0x3400..=0x4DBF
+            | 0x4E00..=0x9FFF
+            | 0xA000..=0xA4CF
+            | 0xAC00..=0xD7A3
+            | 0xF900..=0xFAFF
+            | 0xFE30..=0xFE4F
+            | 0xFF00..=0xFF60
+            | 0xFFE0..=0xFFE6
+            | 0x1F300..=0x1F64F
+            | 0x1F900..=0x1F9FF
+            | 0x20000..=0x3FFFD
+    )
+}
+
+impl From<TokenizeError> for Diagnostic {
+    fn from(e: TokenizeError) -> Self {
+        Self::new(e.message().to_string(), Some(e.span()))
+    }
+}
+
+impl From<ParseError> for Diagnostic {
+    fn from(e: ParseError) -> Self {
+        Self::new(e.to_string(), Some(e.span()))
+    }
+}
+
+impl From<ParseWarning> for Diagnostic {
+    fn from(w: ParseWarning) -> Self {
+        Self::warning(w.to_string(), Some(w.span()))
+    }
+}
+
+impl From<GenerateError> for Diagnostic {
+    fn from(e: GenerateError) -> Self {
+        Self::new(e.to_string(), e.span())
+    }
+}
+
+/// 1回のコンパイルで見つかった診断メッセージの集まり
+#[derive(Debug, Clone, Default)]
+pub struct Diagnostics {
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push(diagnostic);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.diagnostics.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics.iter()
+    }
+
+    pub fn render(&self, file: &SourceFile) -> String {
+        self.diagnostics.iter().map(|d| d.render(file)).collect()
+    }
+}
+
+impl From<Diagnostic> for Diagnostics {
+    fn from(diagnostic: Diagnostic) -> Self {
+        Self {
+            diagnostics: vec![diagnostic],
+        }
+    }
+}
@@ -0,0 +1,346 @@
+use crate::parser::{Node, NodeKind, Type};
+use crate::source::Span;
+use crate::tokenizer::{Token, TokenKind};
+use std::fmt::Write;
+
+/// トークン列を `行:列<TAB>種類<TAB>文字列` の形で1行ずつ並べる
+pub fn tokens(tokens: &[Token]) -> String {
+    let mut s = String::new();
+    for token in tokens {
+        let span = token.span();
+        let category = match token.kind() {
+            TokenKind::Reserved(_) => "reserved",
+            TokenKind::Ident(_) => "ident",
+            TokenKind::Num(..) => "num",
+            TokenKind::Str(_) => "str",
+            TokenKind::Eof => "eof",
+            _ => "keyword",
+        };
+        let _ = writeln!(
+            s,
+            "{}:{}\t{}\t{}",
+            span.line(),
+            span.column(),
+            category,
+            token
+        );
+    }
+    s
+}
+
+/// 構文木をインデント付きの木として出力する
+pub fn ast(program: &[Node]) -> String {
+    let mut s = String::new();
+    for node in program {
+        Tree::from_node(node).write_text(&mut s, 0, None);
+    }
+    s
+}
+
+/// 構文木を JSON 配列として出力する
+pub fn ast_json(program: &[Node]) -> String {
+    let mut s = String::new();
+    s.push('[');
+    for (i, node) in program.iter().enumerate() {
+        if i > 0 {
+            s.push(',');
+        }
+        Tree::from_node(node).write_json(&mut s);
+    }
+    s.push_str("]\n");
+    s
+}
+
+enum Value {
+    Str(String),
+    Num(i64),
+    UNum(u64),
+}
+
+enum Child {
+    One(Tree),
+    Many(Vec<Tree>),
+}
+
+/// テキストと JSON の両方に書き出せるようにした構文木のノード
+struct Tree {
+    kind: &'static str,
+    span: Span,
+    attributes: Vec<(&'static str, Value)>,
+    children: Vec<(&'static str, Child)>,
+}
+
+impl Tree {
+    fn new(kind: &'static str, span: Span) -> Self {
+        Self {
+            kind,
+            span,
+            attributes: vec![],
+            children: vec![],
+        }
+    }
+
+    fn str(mut self, name: &'static str, value: impl ToString) -> Self {
+        self.attributes.push((name, Value::Str(value.to_string())));
+        self
+    }
+
+    fn num(mut self, name: &'static str, value: i64) -> Self {
+        self.attributes.push((name, Value::Num(value)));
+        self
+    }
+
+    /// 符号なしの型なら負の数にせず, その型の幅で表す
+    fn integer(mut self, name: &'static str, value: i64, ty: &Type) -> Self {
+        let value = if ty.is_unsigned() {
+            let bits = ty.size() * 8;
+            let mask = if bits >= 64 {
+                u64::MAX
+            } else {
+                (1 << bits) - 1
+            };
+            Value::UNum(value as u64 & mask)
+        } else {
+            Value::Num(value)
+        };
+        self.attributes.push((name, value));
+        self
+    }
+
+    fn ty(self, name: &'static str, ty: &Type) -> Self {
+        self.str(name, ty)
+    }
+
+    fn child(mut self, name: &'static str, node: &Node) -> Self {
+        self.children
+            .push((name, Child::One(Tree::from_node(node))));
+        self
+    }
+
+    fn optional_child(self, name: &'static str, node: Option<&Node>) -> Self {
+        match node {
+            Some(node) => self.child(name, node),
+            None => self,
+        }
+    }
+
+    fn children(mut self, name: &'static str, nodes: &[Node]) -> Self {
+        self.children.push((
+            name,
+            Child::Many(nodes.iter().map(Tree::from_node).collect()),
+        ));
+        self
+    }
+
+    fn from_node(node: &Node) -> Self {
+        let span = node.span();
+        match node.kind() {
+            NodeKind::Operator2 { op, left, right } => Tree::new("Operator2", span)
+                .str("op", format!("{:?}", op))
+                .child("left", left)
+                .child("right", right),
+            NodeKind::Assign { left, right } => Tree::new("Assign", span)
+                .child("left", left)
+                .child("right", right),
+            NodeKind::CompoundAssign { op, left, right } => Tree::new("CompoundAssign", span)
+                .str("op", format!("{:?}", op))
+                .child("left", left)
+                .child("right", right),
+            NodeKind::LogicalAnd { left, right } => Tree::new("LogicalAnd", span)
+                .child("left", left)
+                .child("right", right),
+            NodeKind::LogicalOr { left, right } => Tree::new("LogicalOr", span)
+                .child("left", left)
+                .child("right", right),
+            NodeKind::Conditional(c) => Tree::new("Conditional", span)
+                .child("condition", c.condition())
+                .child("then", c.then_statement())
+                .optional_child("else", c.else_statement().as_deref()),
+            NodeKind::Cast(v, ty) => Tree::new("Cast", span).ty("type", ty).child("operand", v),
+            NodeKind::Member(base, member) => Tree::new("Member", span)
+                .str("name", member.name().unwrap_or_default())
+                .ty("type", member.ty())
+                .num("offset", member.offset() as i64)
+                .child("base", base),
+            NodeKind::Neg(v) => Tree::new("Neg", span).child("operand", v),
+            NodeKind::Not(v) => Tree::new("Not", span).child("operand", v),
+            NodeKind::BitNot(v) => Tree::new("BitNot", span).child("operand", v),
+            NodeKind::Addr(v) => Tree::new("Addr", span).child("operand", v),
+            NodeKind::Deref(v) => Tree::new("Deref", span).child("operand", v),
+            NodeKind::CallFunction(cf) => Tree::new("CallFunction", span)
+                .str("name", cf.name())
+                .children("args", cf.args()),
+            NodeKind::DefineFunction(df) => {
+                let mut tree = Tree::new("DefineFunction", span)
+                    .str("name", df.name())
+                    .ty("return_type", df.return_type());
+                let params = df
+                    .params()
+                    .iter()
+                    .map(|p| {
+                        Tree::new("Parameter", span)
+                            .str("name", p.name())
+                            .ty("type", p.ty())
+                    })
+                    .collect();
+                tree.children.push(("params", Child::Many(params)));
+                tree.children("statements", df.statements())
+            }
+            NodeKind::IfElse(ie) => Tree::new("IfElse", span)
+                .child("condition", ie.condition())
+                .child("then", ie.then_statement())
+                .optional_child("else", ie.else_statement().as_deref()),
+            NodeKind::For(f) => Tree::new("For", span)
+                .optional_child("init", f.init().as_deref())
+                .optional_child("cond", f.cond().as_deref())
+                .optional_child("next", f.next().as_deref())
+                .child("body", f.body()),
+            NodeKind::DoWhile(d) => Tree::new("DoWhile", span)
+                .child("body", d.body())
+                .child("cond", d.cond()),
+            NodeKind::Switch(sw) => {
+                let cases: Vec<String> = sw.cases().iter().map(|v| v.to_string()).collect();
+                Tree::new("Switch", span)
+                    .str("cases", cases.join(", "))
+                    .num("default", sw.has_default() as i64)
+                    .child("cond", sw.cond())
+                    .child("body", sw.body())
+            }
+            NodeKind::Case(index, statement) => Tree::new("Case", span)
+                .num("index", *index as i64)
+                .child("statement", statement),
+            NodeKind::Default(statement) => {
+                Tree::new("Default", span).child("statement", statement)
+            }
+            NodeKind::Break => Tree::new("Break", span),
+            NodeKind::Continue => Tree::new("Continue", span),
+            NodeKind::Goto(label) => Tree::new("Goto", span).str("label", label),
+            NodeKind::Label(label, statement) => Tree::new("Label", span)
+                .str("label", label)
+                .child("statement", statement),
+            NodeKind::Return(v) => Tree::new("Return", span).optional_child("value", v.as_deref()),
+            NodeKind::LocalVariable(lv) => Tree::new("LocalVariable", span)
+                .str("name", lv.name())
+                .ty("type", lv.ty())
+                .num("offset", lv.offset() as i64),
+            NodeKind::GlobalVariable(gv) => Tree::new("GlobalVariable", span)
+                .str("name", gv.name())
+                .ty("type", gv.ty()),
+            NodeKind::DefineGlobalVariable(dgv) => {
+                let tree = Tree::new("DefineGlobalVariable", span)
+                    .str("name", dgv.name())
+                    .ty("type", dgv.ty());
+                match dgv.init() {
+                    Some(values) => {
+                        let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+                        tree.str("init", values.join(", "))
+                    }
+                    None => tree,
+                }
+            }
+            NodeKind::Num(n, ty) => Tree::new("Num", span)
+                .integer("value", *n, ty)
+                .ty("type", ty),
+            NodeKind::StringLiteral(bytes) => {
+                Tree::new("StringLiteral", span).str("value", bytes.escape_ascii())
+            }
+            NodeKind::Block(statements) => {
+                Tree::new("Block", span).children("statements", statements)
+            }
+            NodeKind::DefineVariable(dv) => Tree::new("DefineVariable", span)
+                .str("name", dv.name())
+                .ty("type", dv.ty()),
+            NodeKind::RelativeAddress(offset, ty) => Tree::new("RelativeAddress", span)
+                .num("offset", *offset as i64)
+                .ty("type", ty),
+        }
+    }
+
+    fn write_text(&self, s: &mut String, indent: usize, role: Option<&str>) {
+        let _ = write!(s, "{:indent$}", "", indent = indent * 2);
+        if let Some(role) = role {
+            let _ = write!(s, "{}: ", role);
+        }
+        s.push_str(self.kind);
+        for (name, value) in &self.attributes {
+            match value {
+                Value::Str(v) => {
+                    let _ = write!(s, " {}={}", name, v);
+                }
+                Value::Num(v) => {
+                    let _ = write!(s, " {}={}", name, v);
+                }
+                Value::UNum(v) => {
+                    let _ = write!(s, " {}={}", name, v);
+                }
+            }
+        }
+        let _ = writeln!(s, " @{}:{}", self.span.line(), self.span.column());
+
+        for (name, child) in &self.children {
+            match child {
+                Child::One(tree) => tree.write_text(s, indent + 1, Some(name)),
+                Child::Many(trees) => {
+                    let _ = writeln!(s, "{:indent$}{}:", "", name, indent = (indent + 1) * 2);
+                    for tree in trees {
+                        tree.write_text(s, indent + 2, None);
+                    }
+                }
+            }
+        }
+    }
+
+    fn write_json(&self, s: &mut String) {
+        let _ = write!(
+            s,
+            "{{\"kind\":{},\"span\":{{\"line\":{},\"column\":{},\"offset\":{}}}",
+            json_string(self.kind),
+            self.span.line(),
+            self.span.column(),
+            self.span.offset()
+        );
+        for (name, value) in &self.attributes {
+            let _ = match value {
+                Value::Str(v) => write!(s, ",\"{}\":{}", name, json_string(v)),
+                Value::Num(v) => write!(s, ",\"{}\":{}", name, v),
+                Value::UNum(v) => write!(s, ",\"{}\":{}", name, v),
+            };
+        }
+        for (name, child) in &self.children {
+            let _ = write!(s, ",\"{}\":", name);
+            match child {
+                Child::One(tree) => tree.write_json(s),
+                Child::Many(trees) => {
+                    s.push('[');
+                    for (i, tree) in trees.iter().enumerate() {
+                        if i > 0 {
+                            s.push(',');
+                        }
+                        tree.write_json(s);
+                    }
+                    s.push(']');
+                }
+            }
+        }
+        s.push('}');
+    }
+}
+
+fn json_string(v: &str) -> String {
+    let mut s = String::from("\"");
+    for c in v.chars() {
+        match c {
+            '"' => s.push_str("\\\""),
+            '\\' => s.push_str("\\\\"),
+            '\n' => s.push_str("\\n"),
+            '\r' => s.push_str("\\r"),
+            '\t' => s.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(s, "\\u{:04x}", c as u32);
+            }
+            c => s.push(c),
+        }
+    }
+    s.push('"');
+    s
+}
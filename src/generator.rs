@@ -1,26 +1,151 @@
-use crate::parser::{DefineVariable, Node, Operator2, Parameter, Type, REGISTER_SIZE};
-use std::collections::HashMap;
+use crate::parser::{
+    CallFunction, DefineGlobalVariable, Node, NodeKind, Operator2, StaticTarget, StaticValue,
+    Switch, Type,
+};
+use crate::source::Span;
+use std::fmt::{Display, Formatter};
+use std::io::Write;
 
-pub struct Generator {
+/// 構文木から Intel 記法のアセンブリを `output` に書き出す
+pub struct Generator<W: Write> {
     next_label: usize,
+    /// `.LC{n}` として `.rodata` に置く文字列リテラル
+    strings: Vec<Vec<u8>>,
+    /// 内側のループほど後ろに積んだ break と continue の飛び先
+    break_labels: Vec<String>,
+    continue_labels: Vec<String>,
+    /// goto のラベルを関数ごとに区別するための関数名
+    function_name: String,
+    /// 今生成している関数の戻り値の型
+    return_type: Type,
+    /// 今生成している switch 文のラベル番号. 内側ほど後ろ
+    switch_labels: Vec<usize>,
+    /// `.Ljt{n}` として `.rodata` に置く switch 文のジャンプテーブル
+    jump_tables: Vec<(usize, Vec<String>)>,
+    output: W,
 }
 
 #[derive(Debug)]
 pub enum GenerateError {
-    NotLeftValue,
-    CallArgsOverFlow,
-    DuplicatedVariable,
-    UndefinedVariable(String),
-    InvalidTypeSize(usize),
+    NotLeftValue(Span),
+    Io(std::io::Error),
+}
+
+impl GenerateError {
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            GenerateError::NotLeftValue(span) => Some(*span),
+            GenerateError::Io(_) => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for GenerateError {
+    fn from(e: std::io::Error) -> Self {
+        GenerateError::Io(e)
+    }
+}
+
+impl Display for GenerateError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GenerateError::NotLeftValue(_) => write!(f, "代入の左辺値ではありません"),
+            GenerateError::Io(e) => write!(f, "アセンブリを出力できません: {}", e),
+        }
+    }
 }
 
 type GenerateResult = Result<(), GenerateError>;
 
-const REGISTERS: [&'static str; 6] = ["rdi", "rsi", "rdx", "rcx", "r8", "r9"];
+const REGISTERS: [&str; 6] = ["rdi", "rsi", "rdx", "rcx", "r8", "r9"];
+
+/// 引数の渡し方
+enum ArgLocation {
+    /// `REGISTERS` の何番目から何個のレジスタで渡すか
+    Register(usize, usize),
+    /// スタックで渡す領域の先頭からのオフセット
+    Stack(usize),
+}
+
+/// System V ABI に従って先頭の引数から順に渡し方を決める. スタックで渡す領域の大きさも返す
+///
+/// 16 バイト以下の構造体は 8 バイトごとにレジスタを使い, 足りなければ丸ごとスタックで渡す.
+/// 16 バイトを超える構造体は常にスタックに値をコピーして渡す.
+fn classify_args<'a>(
+    types: impl IntoIterator<Item = Option<&'a Type>>,
+) -> (Vec<ArgLocation>, usize) {
+    let mut locations = vec![];
+    let mut next_register = 0;
+    let mut stack_size = 0;
+    for ty in types {
+        let size = match ty {
+            Some(ty @ Type::Struct(_)) => ty.aligned_size(),
+            _ => 8,
+        };
+        let count = size / 8;
+        if count <= 2 && next_register + count <= REGISTERS.len() {
+            locations.push(ArgLocation::Register(next_register, count));
+            next_register += count;
+        } else {
+            locations.push(ArgLocation::Stack(stack_size));
+            stack_size += size;
+        }
+    }
+    (locations, stack_size)
+}
+
+impl<W: Write> Generator<W> {
+    pub fn new(output: W) -> Self {
+        Self {
+            next_label: 0,
+            strings: vec![],
+            break_labels: vec![],
+            continue_labels: vec![],
+            function_name: String::new(),
+            return_type: Type::Int,
+            switch_labels: vec![],
+            jump_tables: vec![],
+            output,
+        }
+    }
+
+    pub fn into_output(self) -> W {
+        self.output
+    }
+
+    pub fn gen_program(&mut self, program: &[Node]) -> GenerateResult {
+        writeln!(self.output, ".intel_syntax noprefix")?;
+
+        for node in program {
+            self.gen(node)?;
+        }
+
+        if !self.strings.is_empty() {
+            writeln!(self.output, ".section .rodata")?;
+            for (index, bytes) in self.strings.iter().enumerate() {
+                writeln!(self.output, ".LC{}:", index)?;
+                for byte in bytes.iter().chain(&[0]) {
+                    writeln!(self.output, "  .byte {}", byte)?;
+                }
+            }
+        }
 
-impl Generator {
-    pub fn new() -> Self {
-        Self { next_label: 0 }
+        if !self.jump_tables.is_empty() {
+            writeln!(self.output, ".section .rodata")?;
+            writeln!(self.output, ".align 4")?;
+            for (label, targets) in &self.jump_tables {
+                writeln!(self.output, ".Ljt{}:", label)?;
+                // 位置独立にするため表からの相対位置を置く
+                for target in targets {
+                    writeln!(self.output, "  .long {} - .Ljt{}", target, label)?;
+                }
+            }
+        }
+
+        // 実行可能スタックを要求しないことをリンカに伝える
+        writeln!(self.output, ".section .note.GNU-stack,\"\",@progbits")?;
+
+        Ok(())
     }
 
     fn assign_next_label(&mut self) -> usize {
@@ -29,216 +154,768 @@ impl Generator {
         r
     }
 
+    /// 代入先のアドレスを積む. 配列はアドレスを持っていても代入できない
+    fn gen_assign_target(&mut self, node: &Node) -> GenerateResult {
+        if let Some(Type::Array(..)) = node.declare_type() {
+            return Err(GenerateError::NotLeftValue(node.span()));
+        }
+        self.gen_lval(node)
+    }
+
     pub fn gen_lval(&mut self, node: &Node) -> GenerateResult {
-        match node {
-            Node::Deref(val) => {
-                // println!("  ; deref gen_lval");
+        match node.kind() {
+            NodeKind::Deref(val) => {
                 self.gen(val)?;
-                // println!("  pop rax");
-                // println!("  mov rax [rax]");
-                // println!("  push rax");
             }
-            Node::LocalVariable(var) => {
-                // println!("  ; local variable: {}", var.name());
+            NodeKind::LocalVariable(var) => {
                 let offset = var.offset();
 
-                println!("  mov rax, rbp");
-                println!("  sub rax, {}", offset);
-                println!("  push rax");
+                writeln!(self.output, "  mov rax, rbp")?;
+                writeln!(self.output, "  sub rax, {}", offset)?;
+                writeln!(self.output, "  push rax")?;
+            }
+            NodeKind::GlobalVariable(var) => {
+                self.write_att(format_args!("  leaq {}(%rip), %rax", var.name()))?;
+                writeln!(self.output, "  push rax")?;
+            }
+            NodeKind::Member(base, member) => {
+                // 構造体を返す関数呼び出しは戻り値を置いた領域のアドレスになる
+                match base.kind() {
+                    NodeKind::CallFunction(_) => self.gen(base)?,
+                    _ => self.gen_lval(base)?,
+                }
+                writeln!(self.output, "  pop rax")?;
+                writeln!(self.output, "  add rax, {}", member.offset())?;
+                writeln!(self.output, "  push rax")?;
             }
 
             _ => {
-                return Err(GenerateError::NotLeftValue);
+                return Err(GenerateError::NotLeftValue(node.span()));
             }
         }
 
         Ok(())
     }
 
+    /// 大域的な記号を参照する行を書き出す
+    ///
+    /// Intel 記法では `rax` や `cs` のようにレジスタと同じ名前の記号を参照できないので,
+    /// その行だけ AT&T 記法に切り替える. 記号の定義 (ラベルと `.globl`) は Intel 記法のままでよい.
+    fn write_att(&mut self, line: std::fmt::Arguments) -> GenerateResult {
+        writeln!(self.output, ".att_syntax")?;
+        writeln!(self.output, "{}", line)?;
+        writeln!(self.output, ".intel_syntax noprefix")?;
+        Ok(())
+    }
+
+    /// 初期値があれば `.data` に, なければ `.bss` に置く
+    fn gen_global_variable(&mut self, var: &DefineGlobalVariable) -> GenerateResult {
+        match var.init() {
+            Some(_) => writeln!(self.output, ".data")?,
+            None => writeln!(self.output, ".bss")?,
+        }
+        writeln!(self.output, ".globl {}", var.name())?;
+        writeln!(self.output, ".align {}", var.ty().align())?;
+        writeln!(self.output, "{}:", var.name())?;
+
+        match var.init() {
+            Some(values) => {
+                for value in values {
+                    match value {
+                        StaticValue::Int(n, 1) => writeln!(self.output, "  .byte {}", n)?,
+                        StaticValue::Int(n, 2) => writeln!(self.output, "  .short {}", n)?,
+                        StaticValue::Int(n, 4) => writeln!(self.output, "  .long {}", n)?,
+                        StaticValue::Int(n, _) => writeln!(self.output, "  .quad {}", n)?,
+                        StaticValue::Address(StaticTarget::Symbol(name), offset) => {
+                            self.write_att(format_args!("  .quad {}{:+}", name, offset))?
+                        }
+                        StaticValue::Address(StaticTarget::StringLiteral(bytes), offset) => {
+                            writeln!(self.output, "  .quad .LC{}{:+}", self.strings.len(), offset)?;
+                            self.strings.push(bytes.clone());
+                        }
+                        StaticValue::Zero(size) => writeln!(self.output, "  .zero {}", size)?,
+                    }
+                }
+            }
+            None => writeln!(self.output, "  .zero {}", var.ty().size())?,
+        }
+
+        writeln!(self.output, ".text")?;
+
+        Ok(())
+    }
+
+    /// `rsi` が指す `ty` 型の値を `rax` に読み込む
+    ///
+    /// 配列と構造体はレジスタに載らないので, アドレスのまま値として扱う.
+    fn load(&mut self, ty: Option<Type>) -> GenerateResult {
+        match ty {
+            Some(Type::Array(..) | Type::Struct(_)) => writeln!(self.output, "  mov rax, rsi")?,
+            Some(Type::Char) => writeln!(self.output, "  movsx rax, byte ptr [rsi]")?,
+            Some(Type::UChar) => writeln!(self.output, "  movzx eax, byte ptr [rsi]")?,
+            Some(Type::Short) => writeln!(self.output, "  movsx rax, word ptr [rsi]")?,
+            Some(Type::UShort) => writeln!(self.output, "  movzx eax, word ptr [rsi]")?,
+            Some(Type::Int) => writeln!(self.output, "  movsxd rax, dword ptr [rsi]")?,
+            Some(Type::UInt) => writeln!(self.output, "  mov eax, dword ptr [rsi]")?,
+            _ => writeln!(self.output, "  mov rax, [rsi]")?,
+        }
+
+        Ok(())
+    }
+
+    /// `rax` の値を `ty` 型に変換する
+    ///
+    /// レジスタ上の整数は常に自分の型から 64bit に符号拡張(または0拡張)した形で持つ.
+    /// そのため変換先の型だけを見て切り詰めて拡張し直せばよい.
+    fn cast(&mut self, ty: Option<Type>) -> GenerateResult {
+        match ty {
+            Some(Type::Char) => writeln!(self.output, "  movsx rax, al")?,
+            Some(Type::UChar) => writeln!(self.output, "  movzx eax, al")?,
+            Some(Type::Short) => writeln!(self.output, "  movsx rax, ax")?,
+            Some(Type::UShort) => writeln!(self.output, "  movzx eax, ax")?,
+            Some(Type::Int) => writeln!(self.output, "  movsxd rax, eax")?,
+            Some(Type::UInt) => writeln!(self.output, "  mov eax, eax")?,
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// `rdi` の値を `rax` が指す `ty` 型の領域に書き込む. 構造体は `rdi` が指す先からコピーする
+    fn store(&mut self, ty: Option<Type>) -> GenerateResult {
+        if let Some(Type::Struct(st)) = &ty {
+            for i in 0..st.size() {
+                writeln!(self.output, "  mov r8b, [rdi + {}]", i)?;
+                writeln!(self.output, "  mov [rax + {}], r8b", i)?;
+            }
+            return Ok(());
+        }
+
+        match ty.map(|ty| ty.size()) {
+            Some(1) => writeln!(self.output, "  mov [rax], dil")?,
+            Some(2) => writeln!(self.output, "  mov [rax], di")?,
+            Some(4) => writeln!(self.output, "  mov [rax], edi")?,
+            _ => writeln!(self.output, "  mov [rax], rdi")?,
+        }
+
+        Ok(())
+    }
+
+    /// `rax op rdi` の結果を `rax` に置く. `ty` は両辺の型で, 符号の有無で命令を選ぶ
+    fn gen_operator2(&mut self, op: &Operator2, ty: Option<Type>) -> GenerateResult {
+        // ポインタの比較は符号なしで行う
+        let unsigned = ty.is_some_and(|ty| ty.is_unsigned() || !ty.is_integer());
+
+        match op {
+            Operator2::Add => {
+                writeln!(self.output, "  add rax, rdi")?;
+            }
+            Operator2::Sub => {
+                writeln!(self.output, "  sub rax, rdi")?;
+            }
+            Operator2::Mul => {
+                writeln!(self.output, "  imul rax, rdi")?;
+            }
+            Operator2::Div | Operator2::Mod => {
+                if unsigned {
+                    writeln!(self.output, "  xor edx, edx")?;
+                    writeln!(self.output, "  div rdi")?;
+                } else {
+                    writeln!(self.output, "  cqo")?;
+                    writeln!(self.output, "  idiv rdi")?;
+                }
+                if matches!(op, Operator2::Mod) {
+                    writeln!(self.output, "  mov rax, rdx")?;
+                }
+            }
+            Operator2::Shl => {
+                writeln!(self.output, "  mov rcx, rdi")?;
+                writeln!(self.output, "  shl rax, cl")?;
+            }
+            Operator2::Shr => {
+                writeln!(self.output, "  mov rcx, rdi")?;
+                if unsigned {
+                    writeln!(self.output, "  shr rax, cl")?;
+                } else {
+                    writeln!(self.output, "  sar rax, cl")?;
+                }
+            }
+            Operator2::BitAnd => {
+                writeln!(self.output, "  and rax, rdi")?;
+            }
+            Operator2::BitOr => {
+                writeln!(self.output, "  or rax, rdi")?;
+            }
+            Operator2::BitXor => {
+                writeln!(self.output, "  xor rax, rdi")?;
+            }
+            Operator2::Eq => {
+                writeln!(self.output, "  cmp rax, rdi")?;
+                writeln!(self.output, "  sete al")?;
+                writeln!(self.output, "  movzb rax, al")?;
+            }
+            Operator2::Ne => {
+                writeln!(self.output, "  cmp rax, rdi")?;
+                writeln!(self.output, "  setne al")?;
+                writeln!(self.output, "  movzb rax, al")?;
+            }
+            Operator2::Lt => {
+                writeln!(self.output, "  cmp rax, rdi")?;
+                if unsigned {
+                    writeln!(self.output, "  setb al")?;
+                } else {
+                    writeln!(self.output, "  setl al")?;
+                }
+                writeln!(self.output, "  movzb rax, al")?;
+            }
+            Operator2::Lte => {
+                writeln!(self.output, "  cmp rax, rdi")?;
+                if unsigned {
+                    writeln!(self.output, "  setbe al")?;
+                } else {
+                    writeln!(self.output, "  setle al")?;
+                }
+                writeln!(self.output, "  movzb rax, al")?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `rax` が指す構造体を戻り値として返す形にする
+    fn gen_struct_return(&mut self) -> GenerateResult {
+        let size = self.return_type.size();
+        writeln!(self.output, "  mov rsi, rax")?;
+        if self.return_type.is_returned_in_memory() {
+            // 呼び出し側が用意した領域にコピーして, そのアドレスを返す
+            writeln!(self.output, "  mov rdi, [rbp - 8]")?;
+            for i in 0..size {
+                writeln!(self.output, "  mov r8b, [rsi + {}]", i)?;
+                writeln!(self.output, "  mov [rdi + {}], r8b", i)?;
+            }
+            writeln!(self.output, "  mov rax, rdi")?;
+        } else {
+            // 先頭の 8 バイトを rax, 残りを rdx に詰める
+            for (register, low, bytes) in [("rax", "al", 0..size.min(8)), ("rdx", "dl", 8..size)] {
+                writeln!(self.output, "  mov {}, 0", register)?;
+                for i in bytes.rev() {
+                    writeln!(self.output, "  shl {}, 8", register)?;
+                    writeln!(self.output, "  mov {}, [rsi + {}]", low, i)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 引数を渡して関数を呼ぶ. 評価した引数は先頭から順にスタックに積んである
+    ///
+    /// 式の途中ではスタックに値がいくつ積まれているか分からないので, rsp は実行時に 16 バイト境界に揃える.
+    /// スタックで渡す引数は揃えた後にコピーし, 呼び出し後は rsp を積んだ引数ごと元に戻す.
+    fn gen_aligned_call(
+        &mut self,
+        call_function: &CallFunction,
+        types: &[Option<Type>],
+    ) -> GenerateResult {
+        let (locations, stack_size) = classify_args(types.iter().map(Option::as_ref));
+        writeln!(self.output, "  mov r10, rsp")?;
+        writeln!(self.output, "  and rsp, -16")?;
+        // 元の rsp とスタックで渡す領域を合わせて 16 の倍数にする
+        if (stack_size / 8).is_multiple_of(2) {
+            writeln!(self.output, "  sub rsp, 8")?;
+        }
+        writeln!(self.output, "  push r10")?;
+        if stack_size > 0 {
+            writeln!(self.output, "  sub rsp, {}", stack_size)?;
+        }
+        // 構造体はアドレスが積んであるので, 指す先の値をコピーして渡す
+        for (i, (location, ty)) in locations.iter().zip(types).enumerate() {
+            match (location, ty) {
+                (ArgLocation::Stack(offset), Some(Type::Struct(st))) => {
+                    writeln!(self.output, "  mov r11, [r10 + {}]", i * 8)?;
+                    for j in 0..st.size() {
+                        writeln!(self.output, "  mov al, [r11 + {}]", j)?;
+                        writeln!(self.output, "  mov [rsp + {}], al", offset + j)?;
+                    }
+                }
+                (ArgLocation::Stack(offset), _) => {
+                    writeln!(self.output, "  mov rax, [r10 + {}]", i * 8)?;
+                    writeln!(self.output, "  mov [rsp + {}], rax", offset)?;
+                }
+                (ArgLocation::Register(first, count), Some(Type::Struct(st))) => {
+                    writeln!(self.output, "  mov r11, [r10 + {}]", i * 8)?;
+                    for word in 0..*count {
+                        let register = REGISTERS[first + word];
+                        let (from, to) = (word * 8, st.size().min(word * 8 + 8));
+                        if to - from == 8 {
+                            writeln!(self.output, "  mov {}, [r11 + {}]", register, from)?;
+                            continue;
+                        }
+                        // 端数は構造体の外を読まないように上位のバイトから 1 バイトずつ詰める
+                        for j in (from..to).rev() {
+                            writeln!(self.output, "  shl rax, 8")?;
+                            writeln!(self.output, "  mov al, [r11 + {}]", j)?;
+                        }
+                        writeln!(self.output, "  mov {}, rax", register)?;
+                    }
+                }
+                (ArgLocation::Register(first, _), _) => {
+                    writeln!(
+                        self.output,
+                        "  mov {}, [r10 + {}]",
+                        REGISTERS[*first],
+                        i * 8
+                    )?;
+                }
+            }
+        }
+        // 可変長引数の関数には使ったベクタレジスタの数を al で渡す. 浮動小数点数はまだないので 0
+        if call_function.variadic() {
+            writeln!(self.output, "  mov al, 0")?;
+        }
+        self.write_att(format_args!("  call {}", call_function.name()))?;
+        // スタックで渡した引数を捨てて元の rsp に戻し, 揃える前に積んであった引数も捨てる
+        if stack_size > 0 {
+            writeln!(self.output, "  add rsp, {}", stack_size)?;
+        }
+        writeln!(self.output, "  pop rsp")?;
+        if !types.is_empty() {
+            writeln!(self.output, "  add rsp, {}", types.len() * 8)?;
+        }
+        Ok(())
+    }
+
+    /// ループの本体. 中の break と continue は `.Lend{label}` と `.Lcontinue{label}` に飛ぶ
+    fn gen_loop_body(&mut self, body: &Node, label: usize) -> GenerateResult {
+        self.break_labels.push(format!(".Lend{}", label));
+        self.continue_labels.push(format!(".Lcontinue{}", label));
+        let result = self.gen_statement(body);
+        self.break_labels.pop();
+        self.continue_labels.pop();
+        result
+    }
+
+    /// `rax` の値に合う case に飛ぶ. 値が密に並んでいればジャンプテーブル, 疎なら比較の連続にする
+    fn gen_switch_dispatch(&mut self, switch: &Switch, label: usize) -> GenerateResult {
+        let otherwise = if switch.has_default() {
+            format!(".Ldefault{}", label)
+        } else {
+            format!(".Lend{}", label)
+        };
+        let cases = switch.cases();
+        let (Some(&min), Some(&max)) = (cases.iter().min(), cases.iter().max()) else {
+            writeln!(self.output, "  jmp {}", otherwise)?;
+            return Ok(());
+        };
+
+        let range = max as i128 - min as i128 + 1;
+        if cases.len() >= 4 && range <= 3 * cases.len() as i128 {
+            let mut targets = vec![otherwise.clone(); range as usize];
+            for (index, &value) in cases.iter().enumerate() {
+                targets[(value as i128 - min as i128) as usize] =
+                    format!(".Lcase{}_{}", label, index);
+            }
+            // min を引いた値を符号なしで比べれば範囲外を1回で弾ける
+            writeln!(self.output, "  mov rdi, {}", min)?;
+            writeln!(self.output, "  sub rax, rdi")?;
+            writeln!(self.output, "  cmp rax, {}", range - 1)?;
+            writeln!(self.output, "  ja {}", otherwise)?;
+            writeln!(self.output, "  lea rdi, [rip + .Ljt{}]", label)?;
+            writeln!(self.output, "  movsxd rax, dword ptr [rdi + rax * 4]")?;
+            writeln!(self.output, "  add rax, rdi")?;
+            writeln!(self.output, "  jmp rax")?;
+            self.jump_tables.push((label, targets));
+        } else {
+            for (index, value) in cases.iter().enumerate() {
+                writeln!(self.output, "  mov rdi, {}", value)?;
+                writeln!(self.output, "  cmp rax, rdi")?;
+                writeln!(self.output, "  je .Lcase{}_{}", label, index)?;
+            }
+            writeln!(self.output, "  jmp {}", otherwise)?;
+        }
+        Ok(())
+    }
+
+    /// 文を生成する. 文の前後でスタックの深さは変わらない
+    pub fn gen_statement(&mut self, node: &Node) -> GenerateResult {
+        match node.kind() {
+            NodeKind::IfElse(_)
+            | NodeKind::For(_)
+            | NodeKind::DoWhile(_)
+            | NodeKind::Switch(_)
+            | NodeKind::Case(..)
+            | NodeKind::Default(_)
+            | NodeKind::Break
+            | NodeKind::Continue
+            | NodeKind::Goto(_)
+            | NodeKind::Label(..)
+            | NodeKind::Block(_)
+            | NodeKind::Return(_)
+            | NodeKind::DefineVariable(_) => self.gen(node),
+            // 式文は値を捨てる
+            _ => {
+                self.gen(node)?;
+                writeln!(self.output, "  pop rax")?;
+                Ok(())
+            }
+        }
+    }
+
     pub fn gen(&mut self, node: &Node) -> GenerateResult {
-        dbg!(node);
-        match node {
-            Node::Num(n) => {
-                println!("  push {}", n);
+        match node.kind() {
+            NodeKind::Num(n, _) => {
+                if i32::try_from(*n).is_ok() {
+                    writeln!(self.output, "  push {}", n)?;
+                } else {
+                    // push の即値は 32bit までなので一度レジスタに載せる
+                    writeln!(self.output, "  mov rax, {}", n)?;
+                    writeln!(self.output, "  push rax")?;
+                }
             }
-            Node::LocalVariable(_a) => {
+            NodeKind::LocalVariable(_) | NodeKind::GlobalVariable(_) | NodeKind::Member(..) => {
                 self.gen_lval(node)?;
-                println!("  pop rsi");
-                println!("  mov rax, [rsi]");
-                println!("  push rax");
+                writeln!(self.output, "  pop rsi")?;
+                self.load(node.declare_type())?;
+                writeln!(self.output, "  push rax")?;
+            }
+            NodeKind::Cast(val, ty) => {
+                self.gen(val)?;
+                writeln!(self.output, "  pop rax")?;
+                self.cast(Some(ty.clone()))?;
+                writeln!(self.output, "  push rax")?;
+            }
+            NodeKind::DefineGlobalVariable(var) => {
+                self.gen_global_variable(var)?;
+            }
+            NodeKind::StringLiteral(bytes) => {
+                writeln!(self.output, "  lea rax, [rip + .LC{}]", self.strings.len())?;
+                writeln!(self.output, "  push rax")?;
+                self.strings.push(bytes.clone());
             }
-            Node::RelativeAddress(offset, _) => {
-                println!("  mov rax, rbp");
-                println!("  sub rax, {}", offset);
-                println!("  push rax");
+            NodeKind::RelativeAddress(offset, _) => {
+                writeln!(self.output, "  mov rax, rbp")?;
+                writeln!(self.output, "  sub rax, {}", offset)?;
+                writeln!(self.output, "  push rax")?;
             }
-            Node::DefineVariable(dv) => {
-                println!("  sub rsp, {}", dv.ty().aligned_size());
-                println!("  push rsp");
+            NodeKind::DefineVariable(_) => {
+                // 領域は関数の先頭でまとめて確保している
             }
-            Node::Assign { left, right } => {
-                self.gen_lval(left.as_ref())?;
+            NodeKind::Assign { left, right } => {
+                self.gen_assign_target(left.as_ref())?;
                 self.gen(right.as_ref())?;
 
-                println!("  pop rdi");
-                println!("  pop rax");
-                println!("  mov [rax], rdi");
-                println!("  push rdi");
+                writeln!(self.output, "  pop rdi")?;
+                writeln!(self.output, "  pop rax")?;
+                self.store(left.declare_type())?;
+                writeln!(self.output, "  push rdi")?;
             }
-            Node::Operator2 { op, left, right } => {
+            NodeKind::CompoundAssign { op, left, right } => {
+                self.gen_assign_target(left.as_ref())?;
+                writeln!(self.output, "  mov rsi, [rsp]")?;
+                self.load(left.declare_type())?;
+                writeln!(self.output, "  push rax")?;
+                self.gen(right.as_ref())?;
+
+                // 整数なら `right` の型で計算する
+                let ty = match left.declare_type() {
+                    Some(ty) if ty.is_integer() => right.declare_type(),
+                    ty => ty,
+                };
+                writeln!(self.output, "  pop rdi")?;
+                writeln!(self.output, "  pop rax")?;
+                self.cast(ty.clone())?;
+                self.gen_operator2(op, ty)?;
+                self.cast(left.declare_type())?;
+                writeln!(self.output, "  mov rdi, rax")?;
+                writeln!(self.output, "  pop rax")?;
+                self.store(left.declare_type())?;
+                writeln!(self.output, "  push rdi")?;
+            }
+            NodeKind::Operator2 { op, left, right } => {
                 self.gen(left.as_ref())?;
                 self.gen(right.as_ref())?;
 
-                println!("  pop rdi");
-                println!("  pop rax");
-
-                match op {
-                    Operator2::Add => {
-                        let left_type = left.declare_type().expect("type error");
-                        dbg!(&left_type);
-
-                        match left_type {
-                            Type::Ptr(inner_type) => {
-                                let sz = inner_type.size();
-                                dbg!(sz);
-                                match sz {
-                                    4 => {
-                                        println!("  shl rdi, 2");
-                                    }
-                                    8 => {
-                                        println!("  shl rdi, 3");
-                                    }
-                                    _ => return Err(GenerateError::InvalidTypeSize(sz)),
-                                }
-                            }
-                            _ => {}
-                        }
-                        println!("  add rax, rdi");
-                    }
-                    Operator2::Sub => {
-                        println!("  sub rax, rdi");
-                    }
-                    Operator2::Mul => {
-                        println!("  mul rdi");
-                    }
-                    Operator2::Div => {
-                        println!("  cqo");
-                        println!("  idiv rdi");
-                    }
-                    Operator2::Eq => {
-                        println!("  cmp rax, rdi");
-                        println!("  sete al");
-                        println!("  movzb rax, al");
-                    }
-                    Operator2::Ne => {
-                        println!("  cmp rax, rdi");
-                        println!("  setne al");
-                        println!("  movzb rax, al");
-                    }
-                    Operator2::Lt => {
-                        println!("  cmp rax, rdi");
-                        println!("  setl al");
-                        println!("  movzb rax, al");
-                    }
-                    Operator2::Lte => {
-                        println!("  cmp rax, rdi");
-                        println!("  setle al");
-                        println!("  movzb rax, al");
-                    }
+                writeln!(self.output, "  pop rdi")?;
+                writeln!(self.output, "  pop rax")?;
+                self.gen_operator2(op, left.declare_type())?;
+                self.cast(node.declare_type())?;
+                writeln!(self.output, "  push rax")?;
+            }
+            NodeKind::LogicalAnd { left, right } => {
+                let label = self.assign_next_label();
+                for operand in [left, right] {
+                    self.gen(operand)?;
+                    writeln!(self.output, "  pop rax")?;
+                    writeln!(self.output, "  cmp rax, 0")?;
+                    writeln!(self.output, "  je .Lfalse{}", label)?;
                 }
-
-                println!("  push rax");
+                writeln!(self.output, "  push 1")?;
+                writeln!(self.output, "  jmp .Lend{}", label)?;
+                writeln!(self.output, ".Lfalse{}:", label)?;
+                writeln!(self.output, "  push 0")?;
+                writeln!(self.output, ".Lend{}:", label)?;
+            }
+            NodeKind::LogicalOr { left, right } => {
+                let label = self.assign_next_label();
+                for operand in [left, right] {
+                    self.gen(operand)?;
+                    writeln!(self.output, "  pop rax")?;
+                    writeln!(self.output, "  cmp rax, 0")?;
+                    writeln!(self.output, "  jne .Ltrue{}", label)?;
+                }
+                writeln!(self.output, "  push 0")?;
+                writeln!(self.output, "  jmp .Lend{}", label)?;
+                writeln!(self.output, ".Ltrue{}:", label)?;
+                writeln!(self.output, "  push 1")?;
+                writeln!(self.output, ".Lend{}:", label)?;
+            }
+            NodeKind::Neg(val) => {
+                self.gen(val)?;
+                writeln!(self.output, "  pop rax")?;
+                writeln!(self.output, "  neg rax")?;
+                self.cast(node.declare_type())?;
+                writeln!(self.output, "  push rax")?;
             }
-            Node::Return(val) => {
+            NodeKind::Not(val) => {
                 self.gen(val)?;
-                println!("  pop rax");
-                println!("  mov rsp, rbp");
-                println!("  pop rbp");
-                println!("  ret");
+                writeln!(self.output, "  pop rax")?;
+                writeln!(self.output, "  cmp rax, 0")?;
+                writeln!(self.output, "  sete al")?;
+                writeln!(self.output, "  movzb rax, al")?;
+                writeln!(self.output, "  push rax")?;
+            }
+            NodeKind::BitNot(val) => {
+                self.gen(val)?;
+                writeln!(self.output, "  pop rax")?;
+                writeln!(self.output, "  not rax")?;
+                self.cast(node.declare_type())?;
+                writeln!(self.output, "  push rax")?;
+            }
+            NodeKind::Return(val) => {
+                if let Some(val) = val {
+                    self.gen(val)?;
+                    writeln!(self.output, "  pop rax")?;
+                    if let Type::Struct(_) = self.return_type {
+                        self.gen_struct_return()?;
+                    }
+                }
+                writeln!(self.output, "  mov rsp, rbp")?;
+                writeln!(self.output, "  pop rbp")?;
+                writeln!(self.output, "  ret")?;
             }
 
-            Node::IfElse(if_and_else) => {
+            NodeKind::IfElse(if_and_else) => {
                 let if_label = self.assign_next_label();
-                self.gen(if_and_else.condition().as_ref())?;
-                println!("  pop rax");
-                println!("  cmp rax, 0");
-                println!("  je .Lelse{}", if_label);
-                self.gen(if_and_else.then_statement())?;
-                println!("  jmp .Lend{}", if_label);
-                println!(".Lelse{}:", if_label);
+                self.gen(if_and_else.condition())?;
+                writeln!(self.output, "  pop rax")?;
+                writeln!(self.output, "  cmp rax, 0")?;
+                writeln!(self.output, "  je .Lelse{}", if_label)?;
+                self.gen_statement(if_and_else.then_statement())?;
+                writeln!(self.output, "  jmp .Lend{}", if_label)?;
+                writeln!(self.output, ".Lelse{}:", if_label)?;
                 if let Some(else_statement) = if_and_else.else_statement() {
-                    self.gen(else_statement.as_ref())?;
-                } else {
-                    println!("  push 0");
+                    self.gen_statement(else_statement.as_ref())?;
+                }
+                writeln!(self.output, ".Lend{}:", if_label)?;
+            }
+            NodeKind::Conditional(if_and_else) => {
+                let if_label = self.assign_next_label();
+                self.gen(if_and_else.condition())?;
+                writeln!(self.output, "  pop rax")?;
+                writeln!(self.output, "  cmp rax, 0")?;
+                writeln!(self.output, "  je .Lelse{}", if_label)?;
+                self.gen(if_and_else.then_statement())?;
+                writeln!(self.output, "  jmp .Lend{}", if_label)?;
+                writeln!(self.output, ".Lelse{}:", if_label)?;
+                if let Some(else_expr) = if_and_else.else_statement() {
+                    self.gen(else_expr.as_ref())?;
                 }
-                println!(".Lend{}:", if_label);
+                writeln!(self.output, ".Lend{}:", if_label)?;
             }
-            Node::For(for_) => {
+            NodeKind::For(for_) => {
                 let for_label = self.assign_next_label();
                 if let Some(init) = for_.init() {
-                    self.gen(init)?;
+                    self.gen_statement(init)?;
                 }
-                println!(".Lbegin{}:", for_label);
+                writeln!(self.output, ".Lbegin{}:", for_label)?;
                 if let Some(cond) = for_.cond() {
                     self.gen(cond)?;
-                    println!("  pop rax");
-                    println!("  cmp rax, 0");
-                    println!("  je .Lend{}", for_label);
+                    writeln!(self.output, "  pop rax")?;
+                    writeln!(self.output, "  cmp rax, 0")?;
+                    writeln!(self.output, "  je .Lend{}", for_label)?;
                 }
-                self.gen(for_.body())?;
+                self.gen_loop_body(for_.body(), for_label)?;
+                // continue は next の直前に飛ぶ
+                writeln!(self.output, ".Lcontinue{}:", for_label)?;
                 if let Some(next) = for_.next() {
-                    self.gen(next)?;
+                    self.gen_statement(next)?;
                 }
-                println!("jmp .Lbegin{}", for_label);
-                println!(".Lend{}:", for_label);
+                writeln!(self.output, "jmp .Lbegin{}", for_label)?;
+                writeln!(self.output, ".Lend{}:", for_label)?;
+            }
+            NodeKind::DoWhile(do_while) => {
+                let label = self.assign_next_label();
+                writeln!(self.output, ".Lbegin{}:", label)?;
+                self.gen_loop_body(do_while.body(), label)?;
+                writeln!(self.output, ".Lcontinue{}:", label)?;
+                self.gen(do_while.cond())?;
+                writeln!(self.output, "  pop rax")?;
+                writeln!(self.output, "  cmp rax, 0")?;
+                writeln!(self.output, "  jne .Lbegin{}", label)?;
+                writeln!(self.output, ".Lend{}:", label)?;
+            }
+            NodeKind::Switch(switch) => {
+                let label = self.assign_next_label();
+                self.gen(switch.cond())?;
+                writeln!(self.output, "  pop rax")?;
+                self.gen_switch_dispatch(switch, label)?;
+
+                self.break_labels.push(format!(".Lend{}", label));
+                self.switch_labels.push(label);
+                let result = self.gen_statement(switch.body());
+                self.break_labels.pop();
+                self.switch_labels.pop();
+                result?;
+                writeln!(self.output, ".Lend{}:", label)?;
+            }
+            NodeKind::Case(index, statement) => {
+                let label = self.switch_labels.last().expect("switch の外の case です");
+                writeln!(self.output, ".Lcase{}_{}:", label, index)?;
+                self.gen_statement(statement)?;
+            }
+            NodeKind::Default(statement) => {
+                let label = self
+                    .switch_labels
+                    .last()
+                    .expect("switch の外の default です");
+                writeln!(self.output, ".Ldefault{}:", label)?;
+                self.gen_statement(statement)?;
+            }
+            // 文はスタックを積んだままにしないので, そのまま飛んでよい
+            NodeKind::Break => {
+                let label = self.break_labels.last().expect("ループの外の break です");
+                writeln!(self.output, "  jmp {}", label)?;
+            }
+            NodeKind::Continue => {
+                let label = self
+                    .continue_labels
+                    .last()
+                    .expect("ループの外の continue です");
+                writeln!(self.output, "  jmp {}", label)?;
             }
-            Node::Block(statements) => {
+            NodeKind::Goto(label) => {
+                writeln!(self.output, "  jmp .L.{}.{}", self.function_name, label)?;
+            }
+            NodeKind::Label(label, statement) => {
+                writeln!(self.output, ".L.{}.{}:", self.function_name, label)?;
+                self.gen_statement(statement)?;
+            }
+            NodeKind::Block(statements) => {
                 for s in statements {
-                    self.gen(s)?;
-                    println!("  pop rax");
+                    self.gen_statement(s)?;
                 }
             }
 
-            Node::CallFunction(call_function) => {
-                if call_function.args().len() > 6 {
-                    return Err(GenerateError::CallArgsOverFlow);
-                }
+            NodeKind::CallFunction(call_function) => {
+                let return_type = node.declare_type();
+                // メモリで返す構造体は書き込み先のアドレスを隠れた最初の引数として渡す
+                let in_memory = return_type
+                    .as_ref()
+                    .is_some_and(Type::is_returned_in_memory);
+                let mut types: Vec<Option<Type>> = call_function
+                    .args()
+                    .iter()
+                    .map(Node::declare_type)
+                    .collect();
                 for arg in call_function.args().iter().rev() {
                     self.gen(arg)?;
                 }
-                for (_, register) in call_function.args().iter().zip(REGISTERS) {
-                    println!("  pop {}", register);
+                if let (true, Some(offset)) = (in_memory, call_function.return_buffer()) {
+                    writeln!(self.output, "  lea rax, [rbp - {}]", offset)?;
+                    writeln!(self.output, "  push rax")?;
+                    types.insert(0, None);
+                }
+                self.gen_aligned_call(call_function, &types)?;
+                match (return_type, call_function.return_buffer()) {
+                    (Some(Type::Struct(st)), Some(offset)) if !in_memory => {
+                        // rax と rdx で返された構造体を領域に書き出して, そのアドレスを値にする
+                        writeln!(self.output, "  lea rdi, [rbp - {}]", offset)?;
+                        for i in 0..st.size() {
+                            let (register, low) = if i < 8 { ("rax", "al") } else { ("rdx", "dl") };
+                            writeln!(self.output, "  mov [rdi + {}], {}", i, low)?;
+                            writeln!(self.output, "  shr {}, 8", register)?;
+                        }
+                        writeln!(self.output, "  mov rax, rdi")?;
+                    }
+                    // メモリで返す構造体は rax に書き込み先のアドレスが返る
+                    (Some(Type::Struct(_)), _) => {}
+                    // 戻り値の上位ビットは不定なので型に合わせて拡張する
+                    (ty, _) => self.cast(ty)?,
                 }
-                println!("  call {}", call_function.name());
-                println!("  push rax");
+                writeln!(self.output, "  push rax")?;
             }
-            Node::DefineFunction(define_function) => {
-                println!("{}:", define_function.name());
-                println!("  push rbp");
-                println!("  mov rbp, rsp");
-                for (register, param) in REGISTERS.iter().zip(define_function.params().iter()) {
-                    println!("  push {}", register);
+            NodeKind::DefineFunction(define_function) => {
+                self.function_name = define_function.name().to_string();
+                self.return_type = define_function.return_type().clone();
+                // 別に翻訳した単位からも呼べるように, main に限らず公開する
+                writeln!(self.output, ".globl {}", define_function.name())?;
+                writeln!(self.output, "{}:", define_function.name())?;
+                writeln!(self.output, "  push rbp")?;
+                writeln!(self.output, "  mov rbp, rsp")?;
+                let stack_size = define_function.stack_size().next_multiple_of(16);
+                if stack_size > 0 {
+                    writeln!(self.output, "  sub rsp, {}", stack_size)?;
+                }
+                // 引数は先頭から順に型の大きさずつ rbp より下に割り当てられている.
+                // メモリで返す構造体の書き込み先は隠れた最初の引数
+                let hidden = self.return_type.is_returned_in_memory().then_some(None);
+                let types: Vec<Option<&Type>> = hidden
+                    .into_iter()
+                    .chain(define_function.params().iter().map(|p| Some(p.ty())))
+                    .collect();
+                let (locations, _) = classify_args(types.iter().copied());
+                let mut offset = 0;
+                for (location, ty) in locations.iter().zip(&types) {
+                    let size = ty.map_or(8, Type::aligned_size);
+                    offset += size;
+                    match location {
+                        ArgLocation::Register(first, count) => {
+                            // 領域は 8 の倍数なので構造体の端数もレジスタ丸ごと書き込める
+                            for word in 0..*count {
+                                let register = REGISTERS[first + word];
+                                writeln!(
+                                    self.output,
+                                    "  mov [rbp - {}], {}",
+                                    offset - word * 8,
+                                    register
+                                )?;
+                            }
+                        }
+                        ArgLocation::Stack(from) => {
+                            // 呼び出し側が積んだものが戻りアドレスと rbp の上にある
+                            for word in (0..size).step_by(8) {
+                                writeln!(self.output, "  mov rax, [rbp + {}]", 16 + from + word)?;
+                                writeln!(self.output, "  mov [rbp - {}], rax", offset - word)?;
+                            }
+                        }
+                    }
                 }
-                // println!("  sub rsp, {}", INTEGER_SIZE * 26); // FIXME(higumachan): 一旦26個のローカル変数用のスタックを用意する. 変数定義があるのでもうすでに必要ないが,互換性のために残している.
 
                 for statement in define_function.statements() {
-                    self.gen(&statement)?;
-                    println!("  pop rax");
+                    self.gen_statement(statement)?;
                 }
 
-                println!("  mov rsp, rbp");
-                println!("  pop rbp");
-                println!("  ret");
+                writeln!(self.output, "  mov rsp, rbp")?;
+                writeln!(self.output, "  pop rbp")?;
+                writeln!(self.output, "  ret")?;
             }
-            Node::Addr(val) => {
+            NodeKind::Addr(val) => {
                 self.gen_lval(val.as_ref())?;
             }
-            Node::Deref(val) => {
-                // println!("  ; deref gen");
+            NodeKind::Deref(val) => {
                 self.gen(val.as_ref())?;
-                println!("  pop rsi");
-                println!("  mov rax, [rsi]");
-                println!("  push rax");
+                writeln!(self.output, "  pop rsi")?;
+                self.load(node.declare_type())?;
+                writeln!(self.output, "  push rax")?;
             }
         }
 
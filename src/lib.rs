@@ -0,0 +1,149 @@
+//! C コンパイラ rust-9cc のライブラリ部分.
+//!
+//! ソースから Intel 記法の x86-64 アセンブリまでを `compile` で一度に行うほか,
+//! `tokenize` / `parse` / `generate` で各段階を個別に呼び出せる.
+
+pub mod diagnostic;
+pub mod dump;
+pub mod generator;
+pub mod parser;
+pub mod source;
+pub mod tokenizer;
+pub mod trace;
+
+use crate::diagnostic::{Diagnostic, Diagnostics};
+use crate::generator::Generator;
+use crate::parser::{Node, TokenStream};
+use crate::source::SourceFile;
+use crate::tokenizer::Token;
+use crate::trace::Trace;
+use std::io::Write;
+use std::str::FromStr;
+
+pub use crate::tokenizer::tokenize;
+
+/// `compile` がどの段階まで進んで何を返すか
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Emit {
+    /// `dump::tokens` の形式のトークン列
+    Tokens,
+    /// `dump::ast` の形式の構文木
+    Ast,
+    /// `dump::ast_json` の形式の構文木
+    AstJson,
+    /// アセンブリ
+    #[default]
+    Asm,
+}
+
+impl FromStr for Emit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tokens" => Ok(Emit::Tokens),
+            "ast" => Ok(Emit::Ast),
+            "ast-json" => Ok(Emit::AstJson),
+            "asm" => Ok(Emit::Asm),
+            _ => Err(format!("不明な --emit の指定です: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CompileOptions {
+    emit: Emit,
+    trace: Trace,
+}
+
+impl CompileOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn with_emit(self, emit: Emit) -> Self {
+        Self { emit, ..self }
+    }
+    pub fn with_trace(self, trace: Trace) -> Self {
+        Self { trace, ..self }
+    }
+    pub fn emit(&self) -> Emit {
+        self.emit
+    }
+    pub fn trace(&self) -> Trace {
+        self.trace
+    }
+}
+
+/// 構文解析してトップレベルの定義の列を返す. 警告も必要なら `parse_with_warnings` を使う
+pub fn parse(tokens: Vec<Token>) -> Result<Vec<Node>, Diagnostic> {
+    let mut token_stream = TokenStream::new(tokens);
+    Ok(token_stream.program()?)
+}
+
+/// `parse` と同じだが, 解析できたときは警告も返す. 失敗したときの診断にも警告を含める
+pub fn parse_with_warnings(tokens: Vec<Token>) -> Result<(Vec<Node>, Diagnostics), Diagnostics> {
+    let mut token_stream = TokenStream::new(tokens);
+    let result = token_stream.program();
+    let mut diagnostics = Diagnostics::new();
+    for warning in token_stream.warnings() {
+        diagnostics.push(warning.clone().into());
+    }
+    match result {
+        Ok(program) => Ok((program, diagnostics)),
+        Err(e) => {
+            diagnostics.push(e.into());
+            Err(diagnostics)
+        }
+    }
+}
+
+/// 構文木から生成したアセンブリを `output` に書き出す
+pub fn generate_to<W: Write>(program: &[Node], output: W) -> Result<W, Diagnostic> {
+    let mut generator = Generator::new(output);
+    generator.gen_program(program)?;
+    Ok(generator.into_output())
+}
+
+/// 構文木からアセンブリを生成する
+pub fn generate(program: &[Node]) -> Result<String, Diagnostic> {
+    let assembly = generate_to(program, Vec::new())?;
+    Ok(String::from_utf8(assembly).expect("アセンブリは ASCII で出力している"))
+}
+
+/// `source` を `options.emit()` の段階までコンパイルしてその結果を返す
+pub fn compile(source: &SourceFile, options: &CompileOptions) -> Result<String, Diagnostics> {
+    compile_with_warnings(source, options).map(|(output, _)| output)
+}
+
+/// `compile` と同じだが, コンパイルできたときは警告も返す. 失敗したときの診断にも警告を含める
+pub fn compile_with_warnings(
+    source: &SourceFile,
+    options: &CompileOptions,
+) -> Result<(String, Diagnostics), Diagnostics> {
+    let trace = options.trace();
+
+    let tokens = tokenize(source).map_err(Diagnostic::from)?;
+    trace.tokens(source, &tokens);
+    if options.emit() == Emit::Tokens {
+        return Ok((dump::tokens(&tokens), Diagnostics::new()));
+    }
+
+    let (program, mut diagnostics) = parse_with_warnings(tokens)?;
+    trace.parse_result(source, &program);
+    match options.emit() {
+        Emit::Ast => return Ok((dump::ast(&program), diagnostics)),
+        Emit::AstJson => return Ok((dump::ast_json(&program), diagnostics)),
+        _ => {}
+    }
+
+    let assembly = match generate(&program) {
+        Ok(assembly) => assembly,
+        Err(e) => {
+            diagnostics.push(e);
+            return Err(diagnostics);
+        }
+    };
+    trace.codegen(source, &assembly);
+
+    Ok((assembly, diagnostics))
+}
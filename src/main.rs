@@ -1,30 +1,397 @@
-mod generator;
-mod parser;
-mod tokenizer;
-
-use crate::generator::Generator;
-use crate::parser::TokenStream;
-use crate::tokenizer::tokenize;
+use rust_9cc::source::SourceFile;
+use rust_9cc::trace::Trace;
+use rust_9cc::{compile_with_warnings, CompileOptions, Emit};
 use std::env::args;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{exit, Command, Stdio};
 
-fn main() {
-    let argv: Vec<_> = args().collect();
-    assert_eq!(args().len(), 2);
+const STDIN_PATH: &str = "-";
+const STDIN_NAME: &str = "<stdin>";
+const DEFAULT_EXECUTABLE: &str = "a.out";
+
+#[derive(Debug)]
+enum DriverError {
+    /// 整形済みのコンパイルエラー
+    Compile(String),
+    Other(String),
+}
+
+impl From<String> for DriverError {
+    fn from(message: String) -> Self {
+        DriverError::Other(message)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    /// `-S`: アセンブリ(.s)を出力する
+    Assembly,
+    /// `-c`: オブジェクトファイル(.o)を出力する
+    Object,
+    /// 実行ファイルまでリンクする
+    Executable,
+}
+
+#[derive(Debug)]
+struct Options {
+    mode: Mode,
+    emit: Option<Emit>,
+    output: Option<String>,
+    inputs: Vec<String>,
+    trace: Trace,
+}
+
+impl Options {
+    fn parse(args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut mode = Mode::Executable;
+        let mut emit = None;
+        let mut output = None;
+        let mut inputs = vec![];
+        let mut trace = Trace::from_env()?;
+
+        let mut args = args;
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-S" => mode = Mode::Assembly,
+                "-c" => mode = Mode::Object,
+                "-o" => {
+                    output = Some(
+                        args.next()
+                            .ok_or_else(|| "-o の後に出力ファイル名が必要です".to_string())?,
+                    )
+                }
+                s if s.starts_with("-o") => output = Some(s[2..].to_string()),
+                s if s.starts_with("--emit=") => emit = Some(s["--emit=".len()..].parse()?),
+                s if s.starts_with("--trace=") => {
+                    trace = trace.merge(Trace::parse(&s["--trace=".len()..])?)
+                }
+                s if s.starts_with('-') && s != STDIN_PATH => {
+                    return Err(format!("不明なオプションです: {}", s))
+                }
+                _ => inputs.push(arg),
+            }
+        }
+
+        if inputs.is_empty() {
+            return Err("入力ファイルがありません".to_string());
+        }
+        if (mode != Mode::Executable || emit.is_some()) && output.is_some() && inputs.len() > 1 {
+            return Err(
+                "-S, -c, --emit を複数の入力と一緒に使う場合 -o は指定できません".to_string(),
+            );
+        }
+
+        Ok(Self {
+            mode,
+            emit,
+            output,
+            inputs,
+            trace,
+        })
+    }
+}
 
-    let p = argv[1].as_str();
-    let tokens = tokenize(p).unwrap();
+fn read_source(path: &str) -> std::io::Result<String> {
+    if path == STDIN_PATH {
+        let mut source = String::new();
+        std::io::stdin().read_to_string(&mut source)?;
+        Ok(source)
+    } else {
+        std::fs::read_to_string(path)
+    }
+}
 
-    dbg!(&tokens);
+fn is_c_source(path: &str) -> bool {
+    path == STDIN_PATH || Path::new(path).extension().is_some_and(|ext| ext == "c")
+}
 
-    let mut token_stream = TokenStream::new(tokens);
-    let code = token_stream.program().unwrap();
+fn is_assembly(path: &str) -> bool {
+    Path::new(path).extension().is_some_and(|ext| ext == "s")
+}
 
-    let mut generator = Generator::new();
+/// `foo/bar.c` に対する出力先 `bar.<extension>` (gcc と同様にカレントディレクトリに置く)
+fn output_path(path: &str, extension: &str) -> PathBuf {
+    if path == STDIN_PATH {
+        return PathBuf::from("-").with_extension(extension);
+    }
+    let file_name = Path::new(path).file_name().unwrap_or_default();
+    Path::new(file_name).with_extension(extension)
+}
+
+/// 中間ファイル用の一時ファイルパス
+fn temporary_path(index: usize, extension: &str) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "rust-9cc-{}-{}.{}",
+        std::process::id(),
+        index,
+        extension
+    ))
+}
 
-    println!(".intel_syntax noprefix");
-    println!(".globl main");
+fn read_input(input: &str) -> Result<SourceFile, String> {
+    let contents = read_source(input).map_err(|e| format!("{}: 読み込めません: {}", input, e))?;
+    let file_name = if input == STDIN_PATH {
+        STDIN_NAME
+    } else {
+        input
+    };
+    Ok(SourceFile::new(0, file_name.to_string(), contents))
+}
+
+fn compile_source(source: &SourceFile, trace: &Trace, emit: Emit) -> Result<String, DriverError> {
+    let options = CompileOptions::new().with_emit(emit).with_trace(*trace);
+    match compile_with_warnings(source, &options) {
+        Ok((output, warnings)) => {
+            eprint!("{}", warnings.render(source));
+            Ok(output)
+        }
+        Err(diagnostics) => Err(DriverError::Compile(diagnostics.render(source))),
+    }
+}
+
+/// `#include` などの指令を含むソースか
+fn needs_preprocessor(source: &SourceFile) -> bool {
+    source
+        .contents()
+        .lines()
+        .any(|line| line.trim_start().starts_with('#'))
+}
+
+/// rust-9cc にはプリプロセッサがないので, 指令を含むソースは `cc -E` で展開してから読む
+///
+/// 標準入力から読んだものは `#include "..."` をカレントディレクトリから探すように, そのまま cc の標準入力に渡す.
+fn preprocess(input: &str, source: SourceFile) -> Result<SourceFile, String> {
+    if !needs_preprocessor(&source) {
+        return Ok(source);
+    }
+    let mut command = Command::new("cc");
+    command.arg("-E");
+    let path = if input == STDIN_PATH {
+        command.args(["-x", "c", "-"]).stdin(Stdio::piped());
+        // cc は標準入力をこの名前で行番号の印に書く
+        "<stdin>"
+    } else {
+        command.arg(input);
+        input
+    };
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .map_err(|e| format!("cc を実行できません: {}", e))?;
+    // 出力を読みながら書かないと, 大きなソースでパイプが詰まる
+    let writer = child.stdin.take().map(|mut stdin| {
+        let contents = source.contents().to_string();
+        std::thread::spawn(move || stdin.write_all(contents.as_bytes()))
+    });
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("cc を実行できません: {}", e))?;
+    let written = writer.map(|writer| {
+        writer
+            .join()
+            .expect("cc に書き込むスレッドが異常終了しました")
+    });
+    if !output.status.success() {
+        return Err(format!("cc -E が失敗しました ({})", output.status));
+    }
+    if let Some(Err(e)) = written {
+        return Err(format!("cc にソースを渡せません: {}", e));
+    }
+    let expanded = String::from_utf8_lossy(&output.stdout);
+    let contents = restore_lines(&expanded, path);
+    Ok(SourceFile::new(
+        source.id(),
+        source.name().to_string(),
+        contents,
+    ))
+}
+
+/// `cc -E` の出力を, 元のソースの各行が同じ行番号に来るように並べ直す
+///
+/// 行番号は `# 12 "file"` の形の行で分かる. ヘッダから展開された部分は `#include` の行に続けて書く.
+fn restore_lines(expanded: &str, path: &str) -> String {
+    let mut lines: Vec<String> = vec![String::new()];
+    let mut in_source = false;
+    let mut line = 1;
+    for text in expanded.lines() {
+        if let Some((number, file)) = line_marker(text) {
+            in_source = file == path;
+            if in_source {
+                line = number.max(1);
+            }
+            continue;
+        }
+        if lines.len() < line {
+            lines.resize(line, String::new());
+        }
+        let current = &mut lines[line - 1];
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(text);
+        if in_source {
+            line += 1;
+        }
+    }
+    let mut contents = lines.join("\n");
+    contents.push('\n');
+    contents
+}
+
+/// `# 12 "file" 2` の形の行から行番号とファイル名を取り出す
+fn line_marker(text: &str) -> Option<(usize, String)> {
+    let rest = text.strip_prefix("# ")?;
+    let (number, rest) = rest.split_once(' ')?;
+    let number = number.parse().ok()?;
+    let name = rest.strip_prefix('"')?;
+    let mut file = String::new();
+    let mut chars = name.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => return Some((number, file)),
+            '\\' => file.push(chars.next()?),
+            c => file.push(c),
+        }
+    }
+    None
+}
+
+fn write_file(path: &Path, contents: &str) -> Result<(), String> {
+    std::fs::write(path, contents).map_err(|e| format!("{}: 書き込めません: {}", path.display(), e))
+}
+
+fn run_command(command: &mut Command) -> Result<(), String> {
+    let program = command.get_program().to_string_lossy().to_string();
+    let status = command
+        .status()
+        .map_err(|e| format!("{} を実行できません: {}", program, e))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("{} が失敗しました ({})", program, status))
+    }
+}
+
+/// システムのアセンブラで `.s` を `.o` にする
+fn assemble(input: &Path, output: &Path) -> Result<(), String> {
+    run_command(Command::new("as").arg("-o").arg(output).arg(input))
+}
+
+/// `cc` でオブジェクトファイル等をリンクして実行ファイルを作る
+fn link(inputs: &[PathBuf], output: &str) -> Result<(), String> {
+    run_command(Command::new("cc").arg("-o").arg(output).args(inputs))
+}
+
+fn run(options: &Options) -> Result<(), DriverError> {
+    let mut temporaries = vec![];
+    let result = run_with_temporaries(options, &mut temporaries);
+    for path in temporaries {
+        let _ = std::fs::remove_file(path);
+    }
+    result
+}
+
+fn run_with_temporaries(
+    options: &Options,
+    temporaries: &mut Vec<PathBuf>,
+) -> Result<(), DriverError> {
+    if let Some(emit) = options.emit {
+        for input in options.inputs.iter().filter(|input| is_c_source(input)) {
+            let source = preprocess(input, read_input(input)?)?;
+            let result = compile_source(&source, &options.trace, emit)?;
+            match options.output.as_deref() {
+                None | Some(STDIN_PATH) => print!("{}", result),
+                Some(output) => write_file(Path::new(output), &result)?,
+            }
+        }
+        return Ok(());
+    }
+
+    let mut link_inputs = vec![];
+
+    for input in &options.inputs {
+        match options.mode {
+            Mode::Assembly => {
+                if !is_c_source(input) {
+                    continue;
+                }
+                let source = preprocess(input, read_input(input)?)?;
+                let output = match options.output.as_deref() {
+                    None if input == STDIN_PATH => PathBuf::from(STDIN_PATH),
+                    Some(output) => PathBuf::from(output),
+                    None => output_path(input, "s"),
+                };
+                let assembly = compile_source(&source, &options.trace, Emit::Asm)?;
+                if output == Path::new(STDIN_PATH) {
+                    print!("{}", assembly);
+                } else {
+                    write_file(&output, &assembly)?;
+                }
+            }
+            Mode::Object | Mode::Executable => {
+                if !is_c_source(input) && !is_assembly(input) {
+                    // .o や .a はそのままリンカに渡す
+                    link_inputs.push(PathBuf::from(input));
+                    continue;
+                }
+
+                let object_file = match (options.mode, options.output.as_deref()) {
+                    (Mode::Object, Some(output)) => PathBuf::from(output),
+                    (Mode::Object, None) => output_path(input, "o"),
+                    _ => {
+                        let path = temporary_path(temporaries.len(), "o");
+                        temporaries.push(path.clone());
+                        path
+                    }
+                };
+
+                if is_assembly(input) {
+                    assemble(Path::new(input), &object_file)?;
+                } else {
+                    let source = preprocess(input, read_input(input)?)?;
+                    let assembly_file = temporary_path(temporaries.len(), "s");
+                    temporaries.push(assembly_file.clone());
+                    write_file(
+                        &assembly_file,
+                        &compile_source(&source, &options.trace, Emit::Asm)?,
+                    )?;
+                    assemble(&assembly_file, &object_file)?;
+                }
+                link_inputs.push(object_file);
+            }
+        }
+    }
+
+    if options.mode == Mode::Executable {
+        link(
+            &link_inputs,
+            options.output.as_deref().unwrap_or(DEFAULT_EXECUTABLE),
+        )?;
+    }
+
+    Ok(())
+}
+
+fn main() {
+    let options = Options::parse(args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("rust-9cc: {}", e);
+        eprintln!(
+            "usage: rust-9cc [-S | -c] [-o <file>] [--emit=tokens|ast|ast-json|asm] [--trace=tokens,parse,codegen] <file>... (`-` で標準入力から読み込む. `#` で始まる指令は cc -E で展開する)"
+        );
+        exit(1);
+    });
 
-    for line in &code {
-        generator.gen(&line).unwrap();
+    match run(&options) {
+        Ok(()) => {}
+        Err(DriverError::Compile(diagnostic)) => {
+            eprint!("{}", diagnostic);
+            exit(1);
+        }
+        Err(DriverError::Other(e)) => {
+            eprintln!("rust-9cc: {}", e);
+            exit(1);
+        }
     }
 }
@@ -1,45 +1,111 @@
-use crate::tokenizer::Token;
-use std::collections::HashMap;
+use crate::source::Span;
+use crate::tokenizer::{Token, TokenKind};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 
 use std::fmt::{Display, Formatter};
-use std::iter::Peekable;
+use std::rc::Rc;
 
-use std::vec::IntoIter;
+/// 関数の中で宣言された名前
+#[derive(Debug, Clone)]
+enum LocalSymbol {
+    /// `rbp` からのオフセットと型
+    Variable(usize, Type),
+    Typedef(Type),
+    EnumConstant(i64),
+}
 
+/// ローカル変数のスタック上の位置を決め, ブロックごとのスコープで名前を管理する
 struct LocalVariableAssigner {
-    local_variables: HashMap<String, (usize, Type)>,
+    /// 内側のスコープほど後ろに積む. 関数の外では空
+    scopes: Vec<HashMap<String, LocalSymbol>>,
+    /// 構造体と共用体のタグ. 名前とは別の名前空間で, `scopes` と同じように積む
+    tag_scopes: Vec<HashMap<String, StructType>>,
     current_offset: usize,
 }
 
 impl LocalVariableAssigner {
     pub fn new() -> Self {
         Self {
-            local_variables: HashMap::new(),
+            scopes: vec![],
+            tag_scopes: vec![],
             current_offset: 0,
         }
     }
 
     fn clear(&mut self) {
         self.current_offset = 0;
-        self.local_variables.clear();
+        self.scopes.clear();
+        self.tag_scopes.clear();
+    }
+
+    fn in_function(&self) -> bool {
+        !self.scopes.is_empty()
+    }
+
+    fn enter_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+        self.tag_scopes.push(HashMap::new());
+    }
+
+    fn leave_scope(&mut self) {
+        self.scopes.pop();
+        self.tag_scopes.pop();
+    }
+
+    /// 一番内側のスコープに名前を宣言する. 同じスコープで宣言済みなら `false`
+    fn declare(&mut self, name: &str, symbol: LocalSymbol) -> bool {
+        let scope = self.scopes.last_mut().expect("関数の外です");
+        if scope.contains_key(name) {
+            return false;
+        }
+        scope.insert(name.to_string(), symbol);
+        true
     }
 
+    /// 変数に領域を割り当てる. 同じスコープで宣言済みなら `None`
+    ///
+    /// 内側のブロックの変数にも関数全体で別々の領域を割り当てる.
     pub fn assign_local_variable(&mut self, variable: &DefineVariable) -> Option<usize> {
-        let variable_name = variable.name();
-        if !self.local_variables.contains_key(variable_name) {
-            self.current_offset += variable.ty.aligned_size();
-            self.local_variables.insert(
-                variable_name.to_string(),
-                (self.current_offset, variable.ty().clone()),
-            );
-            Some(variable.ty.size())
-        } else {
-            None
+        let offset = self.current_offset + variable.ty.aligned_size();
+        if !self.declare(
+            variable.name(),
+            LocalSymbol::Variable(offset, variable.ty().clone()),
+        ) {
+            return None;
         }
+        self.current_offset = offset;
+        Some(variable.ty.size())
+    }
+
+    /// 名前のない一時的な領域を割り当ててオフセットを返す
+    fn assign_temporary(&mut self, ty: &Type) -> usize {
+        self.current_offset += ty.aligned_size();
+        self.current_offset
     }
 
-    pub fn get_local_variable(&mut self, variable_name: &str) -> Option<(usize, Type)> {
-        self.local_variables.get(variable_name).cloned()
+    /// 内側のスコープから順に名前を探す
+    fn get(&self, name: &str) -> Option<&LocalSymbol> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    /// 一番内側のスコープにタグを宣言する
+    fn declare_tag(&mut self, tag: String, st: StructType) {
+        let scope = self.tag_scopes.last_mut().expect("関数の外です");
+        scope.insert(tag, st);
+    }
+
+    /// 内側のスコープから順にタグを探す
+    fn get_tag(&self, tag: &str) -> Option<&StructType> {
+        self.tag_scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(tag))
+    }
+
+    /// 一番内側のスコープで宣言したタグ
+    fn get_tag_in_current_scope(&self, tag: &str) -> Option<&StructType> {
+        self.tag_scopes.last().and_then(|scope| scope.get(tag))
     }
 }
 
@@ -63,18 +129,314 @@ impl Parameter {
 
 #[derive(Debug)]
 pub enum ParseError {
-    ExpectReserved(String),
-    ExpectNumber,
-    ExpectFunctionDefine,
-    ExpectInt(Token),
-    ExpectIdent,
-    NotDefinedVariable(String),
-    NotDefinedFunction(String),
+    ExpectReserved(String, Token),
+    ExpectNumber(Token),
+    ExpectFunctionDefine(Span),
+    ExpectType(Token),
+    ExpectIdent(Token),
+    NotDefinedVariable(String, Span),
+    NotDefinedFunction(String, Span),
+    UndeterminedType(Span),
+    InvalidOperands(&'static str, Span),
+    NotConstant(Span),
+    InvalidInitializer(&'static str, Span),
+    InvalidArrayLength(&'static str, Span),
+    InvalidTypeSpecifier(Span),
+    NotStruct(Span),
+    NoMember(String, Span),
+    RedefinedStruct(String, Span),
+    IncompleteMember(String, Span),
+    DuplicatedVariable(String, Span),
+    BreakOutsideLoop(Span),
+    ContinueOutsideLoop(Span),
+    NotDefinedLabel(String, Span),
+    DuplicatedLabel(String, Span),
+    RedefinedFunction(String, Span),
+    RedefinedVariable(String, Span),
+    OutsideSwitch(&'static str, Span),
+    DuplicatedCase(i64, Span),
+    DuplicatedDefault(Span),
+    ConflictingTypes(String, Span),
+    /// 関数名, 必要な数, 可変長引数か, 渡した数
+    ArgumentCount(String, usize, bool, usize, Span),
+    /// 関数名, 何番目か, 渡した型, 必要な型
+    ArgumentType(String, usize, Type, Type, Span),
+    ReturnValueInVoidFunction(Span),
+    /// 代入する値の型, 代入先の型
+    AssignType(Type, Type, Span),
+    /// 返す値の型, 戻り値の型
+    ReturnType(Type, Type, Span),
+}
+
+impl ParseError {
+    pub fn span(&self) -> Span {
+        match self {
+            ParseError::ExpectReserved(_, t)
+            | ParseError::ExpectNumber(t)
+            | ParseError::ExpectType(t)
+            | ParseError::ExpectIdent(t) => t.span(),
+            ParseError::ExpectFunctionDefine(span)
+            | ParseError::NotDefinedVariable(_, span)
+            | ParseError::NotDefinedFunction(_, span)
+            | ParseError::UndeterminedType(span)
+            | ParseError::InvalidOperands(_, span)
+            | ParseError::NotConstant(span)
+            | ParseError::InvalidInitializer(_, span)
+            | ParseError::InvalidArrayLength(_, span)
+            | ParseError::InvalidTypeSpecifier(span)
+            | ParseError::NotStruct(span)
+            | ParseError::NoMember(_, span)
+            | ParseError::RedefinedStruct(_, span)
+            | ParseError::IncompleteMember(_, span)
+            | ParseError::DuplicatedVariable(_, span)
+            | ParseError::BreakOutsideLoop(span)
+            | ParseError::ContinueOutsideLoop(span)
+            | ParseError::NotDefinedLabel(_, span)
+            | ParseError::DuplicatedLabel(_, span)
+            | ParseError::RedefinedFunction(_, span)
+            | ParseError::RedefinedVariable(_, span)
+            | ParseError::OutsideSwitch(_, span)
+            | ParseError::DuplicatedCase(_, span)
+            | ParseError::DuplicatedDefault(span)
+            | ParseError::ConflictingTypes(_, span)
+            | ParseError::ArgumentCount(.., span)
+            | ParseError::ArgumentType(.., span)
+            | ParseError::ReturnValueInVoidFunction(span)
+            | ParseError::AssignType(.., span)
+            | ParseError::ReturnType(.., span) => *span,
+        }
+    }
 }
 
 impl Display for ParseError {
-    fn fmt(&self, _f: &mut Formatter<'_>) -> std::fmt::Result {
-        todo!()
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::ExpectReserved(op, t) => {
+                write!(f, "'{}' が必要ですが '{}' があります", op, t)
+            }
+            ParseError::ExpectNumber(t) => write!(f, "数が必要ですが '{}' があります", t),
+            ParseError::ExpectFunctionDefine(_) => write!(f, "関数定義ではありません"),
+            ParseError::ExpectType(t) => write!(f, "型名が必要ですが '{}' があります", t),
+            ParseError::ExpectIdent(t) => write!(f, "識別子が必要ですが '{}' があります", t),
+            ParseError::NotDefinedVariable(name, _) => {
+                write!(f, "変数 '{}' は定義されていません", name)
+            }
+            ParseError::NotDefinedFunction(name, _) => {
+                write!(f, "関数 '{}' は定義されていません", name)
+            }
+            ParseError::UndeterminedType(_) => write!(f, "式の型が決まりません"),
+            ParseError::InvalidOperands(message, _) => write!(f, "{}", message),
+            ParseError::NotConstant(_) => write!(f, "定数式ではありません"),
+            ParseError::InvalidInitializer(message, _) => write!(f, "{}", message),
+            ParseError::InvalidArrayLength(message, _) => write!(f, "{}", message),
+            ParseError::InvalidTypeSpecifier(_) => write!(f, "型指定子の組み合わせが不正です"),
+            ParseError::NotStruct(_) => write!(f, "構造体でも共用体でもありません"),
+            ParseError::NoMember(name, _) => write!(f, "メンバ '{}' はありません", name),
+            ParseError::RedefinedStruct(tag, _) => {
+                write!(f, "'{}' は既に定義されています", tag)
+            }
+            ParseError::IncompleteMember(name, _) => {
+                write!(f, "メンバ '{}' の型は大きさが決まっていません", name)
+            }
+            ParseError::DuplicatedVariable(name, _) => {
+                write!(f, "'{}' は同じスコープで既に宣言されています", name)
+            }
+            ParseError::BreakOutsideLoop(_) => {
+                write!(f, "break がループと switch の外にあります")
+            }
+            ParseError::ContinueOutsideLoop(_) => write!(f, "continue がループの外にあります"),
+            ParseError::NotDefinedLabel(name, _) => {
+                write!(f, "ラベル '{}' は定義されていません", name)
+            }
+            ParseError::DuplicatedLabel(name, _) => {
+                write!(f, "ラベル '{}' は既に定義されています", name)
+            }
+            ParseError::RedefinedFunction(name, _) => {
+                write!(f, "関数 '{}' は既に定義されています", name)
+            }
+            ParseError::RedefinedVariable(name, _) => {
+                write!(f, "変数 '{}' は既に定義されています", name)
+            }
+            ParseError::OutsideSwitch(keyword, _) => {
+                write!(f, "{} が switch の外にあります", keyword)
+            }
+            ParseError::DuplicatedCase(value, _) => write!(f, "case {} が重複しています", value),
+            ParseError::DuplicatedDefault(_) => write!(f, "default が重複しています"),
+            ParseError::ConflictingTypes(name, _) => {
+                write!(f, "'{}' の型が以前の宣言と一致しません", name)
+            }
+            ParseError::ArgumentCount(name, expected, variadic, actual, _) => write!(
+                f,
+                "関数 '{}' の引数は {} 個{}ですが {} 個あります",
+                name,
+                expected,
+                if *variadic { "以上" } else { "" },
+                actual
+            ),
+            ParseError::ArgumentType(name, index, actual, expected, _) => write!(
+                f,
+                "関数 '{}' の {} 番目の引数に {} は渡せません ({} が必要です)",
+                name, index, actual, expected
+            ),
+            ParseError::ReturnValueInVoidFunction(_) => {
+                write!(f, "void を返す関数では値を返せません")
+            }
+            ParseError::AssignType(actual, expected, _) => {
+                write!(f, "{} に {} は代入できません", expected, actual)
+            }
+            ParseError::ReturnType(actual, expected, _) => {
+                write!(f, "{} を返す関数で {} は返せません", expected, actual)
+            }
+        }
+    }
+}
+
+/// コンパイルは続けられるが疑わしい書き方
+#[derive(Debug, Clone)]
+pub enum ParseWarning {
+    UndeclaredFunction(String, Span),
+    /// 関数名, 何番目か, 渡した型, 必要な型
+    IncompatiblePointer(String, usize, Type, Type, Span),
+    MissingReturnValue(Span),
+    /// 代入する値の型, 代入先の型
+    IncompatiblePointerAssign(Type, Type, Span),
+    /// 返す値の型, 戻り値の型
+    IncompatiblePointerReturn(Type, Type, Span),
+}
+
+impl ParseWarning {
+    pub fn span(&self) -> Span {
+        match self {
+            ParseWarning::UndeclaredFunction(_, span)
+            | ParseWarning::IncompatiblePointer(.., span)
+            | ParseWarning::MissingReturnValue(span)
+            | ParseWarning::IncompatiblePointerAssign(.., span)
+            | ParseWarning::IncompatiblePointerReturn(.., span) => *span,
+        }
+    }
+}
+
+impl Display for ParseWarning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseWarning::UndeclaredFunction(name, _) => {
+                write!(
+                    f,
+                    "関数 '{}' は宣言されていません. int を返すものとします",
+                    name
+                )
+            }
+            ParseWarning::IncompatiblePointer(name, index, actual, expected, _) => write!(
+                f,
+                "関数 '{}' の {} 番目の引数の型 {} は {} と互換性がありません",
+                name, index, actual, expected
+            ),
+            ParseWarning::MissingReturnValue(_) => {
+                write!(f, "値を返す関数で return に値がありません")
+            }
+            ParseWarning::IncompatiblePointerAssign(actual, expected, _) => write!(
+                f,
+                "{} に互換性のない型 {} を代入しています",
+                expected, actual
+            ),
+            ParseWarning::IncompatiblePointerReturn(actual, expected, _) => write!(
+                f,
+                "{} を返す関数で互換性のない型 {} を返しています",
+                expected, actual
+            ),
+        }
+    }
+}
+
+/// 関数の戻り値と仮引数の型
+#[derive(Debug, Clone, PartialEq)]
+struct FunctionType {
+    return_type: Type,
+    /// `int f();` のように仮引数を書かなければ None で, 呼び出しの引数は確かめない
+    params: Option<Vec<Type>>,
+    variadic: bool,
+}
+
+impl FunctionType {
+    /// 同じ関数の宣言として矛盾しないか
+    fn is_compatible(&self, other: &Self) -> bool {
+        self.return_type == other.return_type
+            && match (&self.params, &other.params) {
+                (Some(l), Some(r)) => l == r && self.variadic == other.variadic,
+                _ => true,
+            }
+    }
+}
+
+/// 代入と同じ規則で値を変換した結果
+enum Conversion {
+    Converted(Node),
+    /// 互換性のないポインタ. 警告を出してそのまま使う. 値の型も持つ
+    IncompatiblePointer(Node, Type),
+    /// 変換できない値の型
+    Invalid(Type),
+}
+
+/// 関数宣言の `( ... )` の中身
+struct ParamList {
+    /// 仮引数の名前とその位置, 型. 名前を省いたときは名前があるべき位置のトークンを持つ
+    params: Vec<(Result<String, Token>, Span, Type)>,
+    /// `()` のように何も書いていない
+    unspecified: bool,
+    variadic: bool,
+}
+
+/// 変数の初期化子
+enum Initializer {
+    Expr(Node),
+    /// 文字の配列を初期化する文字列リテラル. 終端の 0 は含まない
+    String(Vec<u8>, Span),
+    List(Vec<Initializer>, Span),
+}
+
+impl Initializer {
+    /// 大きさを省略した配列の要素数
+    fn len(&self) -> usize {
+        match self {
+            Initializer::Expr(_) => 1,
+            Initializer::String(bytes, _) => bytes.len() + 1,
+            Initializer::List(elements, _) => elements.len(),
+        }
+    }
+}
+
+/// ファイルスコープで宣言された名前
+#[derive(Debug, Clone)]
+enum GlobalSymbol {
+    Function(FunctionType),
+    Variable(Type),
+    /// `typedef` で付けた型の名前
+    Typedef(Type),
+    /// 列挙定数
+    EnumConstant(i64),
+}
+
+/// 先読みできるトークン列. キャストと括弧で囲まれた式を区別するために2つ先まで見る
+struct Tokens {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Tokens {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<&Token> {
+        self.tokens.get(self.position + n)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        if token.is_some() {
+            self.position += 1;
+        }
+        token
     }
 }
 
@@ -83,21 +445,53 @@ pub const REGISTER_SIZE: usize = 8;
 pub type ParseResult<T> = std::result::Result<T, ParseError>;
 
 pub struct TokenStream {
-    inner: Peekable<IntoIter<Token>>,
+    inner: Tokens,
     local_variables: LocalVariableAssigner,
-    global_symbols: HashMap<String, Option<Type>>,
+    global_symbols: HashMap<String, GlobalSymbol>,
+    struct_tags: HashMap<String, StructType>,
+    /// 本体まで定義した関数. 同じ関数を二度定義していないか確かめる
+    defined_functions: HashSet<String>,
+    /// 初期化子付きで定義したグローバル変数
+    defined_variables: HashSet<String>,
+    /// 初期化子のないグローバル変数の定義. 最後まで初期化子付きの定義がなければ 0 で定義する
+    tentative_definitions: Vec<Node>,
+    /// 今読んでいる文を囲むループの数. break と continue を書けるかを判断する
+    loop_depth: usize,
+    /// 今読んでいる switch 文. 内側ほど後ろに積む
+    switches: Vec<Switch>,
+    /// 関数の中で定義したラベル
+    labels: HashSet<String>,
+    /// 関数の中の goto の飛び先. 関数の終わりで定義されているか確かめる
+    gotos: Vec<(String, Span)>,
+    /// 今読んでいる関数の戻り値の型
+    return_type: Type,
+    warnings: Vec<ParseWarning>,
 }
 
 impl TokenStream {
     pub fn new(tokens: Vec<Token>) -> Self {
         Self {
-            inner: tokens.into_iter().peekable(),
+            inner: Tokens {
+                tokens,
+                position: 0,
+            },
             local_variables: LocalVariableAssigner::new(),
             global_symbols: HashMap::new(),
+            struct_tags: HashMap::new(),
+            defined_functions: HashSet::new(),
+            defined_variables: HashSet::new(),
+            tentative_definitions: vec![],
+            loop_depth: 0,
+            switches: vec![],
+            labels: HashSet::new(),
+            gotos: vec![],
+            return_type: Type::Int,
+            warnings: vec![],
         }
     }
 
     pub fn primary(&mut self) -> ParseResult<Node> {
+        let span = self.peek_span();
         if self.consume_reserve("(") {
             let node = self.expr()?;
             self.expect_reserve(")")?;
@@ -114,66 +508,176 @@ impl TokenStream {
                         args.push(self.expr()?);
                     }
                 }
-                Ok(Node::CallFunction(CallFunction::new(
-                    ident_name,
-                    args,
-                    Some(Type::Int),
-                )))
+                let function = match self.global_symbols.get(&ident_name) {
+                    Some(GlobalSymbol::Function(function)) => function.clone(),
+                    // 宣言されていない関数は int を返すものとして扱う
+                    _ => {
+                        self.warnings
+                            .push(ParseWarning::UndeclaredFunction(ident_name.clone(), span));
+                        FunctionType {
+                            return_type: Type::Int,
+                            params: None,
+                            variadic: false,
+                        }
+                    }
+                };
+                let args = self.convert_arguments(&ident_name, &function, args, span)?;
+                // 構造体の戻り値は呼び出し側に用意した領域に受け取る
+                let return_buffer = match function.return_type {
+                    Type::Struct(_) => {
+                        Some(self.local_variables.assign_temporary(&function.return_type))
+                    }
+                    _ => None,
+                };
+                // プロトタイプのない関数は可変長引数かもしれない
+                let variadic = function.variadic || function.params.is_none();
+                Ok(Node::new(
+                    NodeKind::CallFunction(CallFunction::new(
+                        ident_name,
+                        args,
+                        Some(function.return_type),
+                        return_buffer,
+                        variadic,
+                    )),
+                    span,
+                ))
+            } else if let Some(symbol) = self.local_variables.get(&ident_name) {
+                match symbol.clone() {
+                    LocalSymbol::Variable(offset, ty) => Ok(Node::new(
+                        NodeKind::LocalVariable(LocalVariable::new(ident_name, offset, ty)),
+                        span,
+                    )),
+                    LocalSymbol::EnumConstant(value) => {
+                        Ok(Node::new(NodeKind::Num(value, Type::Int), span))
+                    }
+                    LocalSymbol::Typedef(_) => {
+                        Err(ParseError::NotDefinedVariable(ident_name, span))
+                    }
+                }
             } else {
-                let (offset, ty) = self
-                    .local_variables
-                    .get_local_variable(ident_name.as_str())
-                    .ok_or_else(|| ParseError::NotDefinedVariable(ident_name.to_string()))?
-                    .clone();
-                Ok(Node::LocalVariable(LocalVariable::new(
-                    ident_name, offset, ty,
-                )))
+                match self.global_symbols.get(&ident_name) {
+                    Some(GlobalSymbol::Variable(ty)) => {
+                        let ty = ty.clone();
+                        Ok(Node::new(
+                            NodeKind::GlobalVariable(GlobalVariable::new(ident_name, ty)),
+                            span,
+                        ))
+                    }
+                    Some(GlobalSymbol::EnumConstant(value)) => {
+                        Ok(Node::new(NodeKind::Num(*value, Type::Int), span))
+                    }
+                    _ => Err(ParseError::NotDefinedVariable(ident_name, span)),
+                }
+            }
+        } else if let Some(mut bytes) = self.consume_str() {
+            // 隣接する文字列リテラルは1つにつなげる
+            while let Some(next) = self.consume_str() {
+                bytes.extend(next);
             }
+            Ok(Node::new(NodeKind::StringLiteral(bytes), span))
         } else {
-            let number = self.expect_number()?;
-            Ok(Node::Num(number))
+            let (number, ty) = self.expect_number()?;
+            Ok(Node::new(NodeKind::Num(number, ty), span))
+        }
+    }
+
+    pub fn postfix(&mut self) -> ParseResult<Node> {
+        let mut node = self.primary()?;
+
+        loop {
+            let span = self.peek_span();
+            if self.consume_reserve("[") {
+                let index = self.expr()?;
+                self.expect_reserve("]")?;
+                node = Node::new(
+                    NodeKind::Deref(Box::new(self.new_add(node, index, span)?)),
+                    span,
+                );
+            } else if self.consume_reserve(".") {
+                node = self.new_member(node, span)?;
+            } else if self.consume_reserve("->") {
+                let base = Node::new(NodeKind::Deref(Box::new(node)), span);
+                node = self.new_member(base, span)?;
+            } else if self.consume_reserve("++") {
+                node = self.new_post_increment(node, 1, span)?;
+            } else if self.consume_reserve("--") {
+                node = self.new_post_increment(node, -1, span)?;
+            } else {
+                break;
+            }
         }
+
+        Ok(node)
     }
 
     pub fn unary(&mut self) -> ParseResult<Node> {
+        let span = self.peek_span();
         if self.consume_sizeof() {
+            let ty = if self.at_type_name_in_parens() {
+                self.expect_reserve("(")?;
+                let ty = self.type_name()?;
+                self.expect_reserve(")")?;
+                ty
+            } else {
+                // 配列はポインタに変換する前の型の大きさになる
+                self.unary()?
+                    .declare_type()
+                    .ok_or(ParseError::UndeterminedType(span))?
+            };
+            Ok(Node::new(
+                NodeKind::Num(ty.size() as i64, Type::ULong),
+                span,
+            ))
+        } else if self.at_type_name_in_parens() {
             self.expect_reserve("(")?;
-            let un = self.unary()?;
-            dbg!(&un);
+            let ty = self.type_name()?;
             self.expect_reserve(")")?;
-            Ok(Node::Num(
-                un.declare_type().expect("unaryは必ず返り値型を持つ").size() as i64,
-            ))
+            let operand = self.unary()?;
+            Ok(Node::new(NodeKind::Cast(Box::new(operand), ty), span))
         } else if self.consume_reserve("+") {
-            Ok(self.primary()?)
+            Ok(self.unary()?)
         } else if self.consume_reserve("-") {
-            Ok(Node::new_op2(
-                Operator2::Sub,
-                Box::new(Node::Num(0)),
-                Box::new(self.primary()?),
-            ))
+            let operand = self.unary()?.promote();
+            Ok(Node::new(NodeKind::Neg(Box::new(operand)), span))
+        } else if self.consume_reserve("!") {
+            Ok(Node::new(NodeKind::Not(Box::new(self.unary()?)), span))
+        } else if self.consume_reserve("~") {
+            let operand = self.unary()?.promote();
+            Ok(Node::new(NodeKind::BitNot(Box::new(operand)), span))
+        } else if self.consume_reserve("++") {
+            let operand = self.unary()?;
+            self.new_compound_assign(Operator2::Add, operand, Node::num(1, span), span)
+        } else if self.consume_reserve("--") {
+            let operand = self.unary()?;
+            self.new_compound_assign(Operator2::Sub, operand, Node::num(1, span), span)
         } else if self.consume_reserve("*") {
-            Ok(Node::Deref(
-                self.unary()?.array_access_to_relative_address().into(),
+            Ok(Node::new(
+                NodeKind::Deref(self.unary()?.array_access_to_relative_address().into()),
+                span,
             ))
         } else if self.consume_reserve("&") {
-            Ok(Node::Addr(self.unary()?.into()))
+            Ok(Node::new(NodeKind::Addr(self.unary()?.into()), span))
         } else {
-            Ok(self.primary()?)
+            self.postfix()
         }
     }
 
     pub fn mul(&mut self) -> ParseResult<Node> {
-        let mut node = self.unary()?.array_access_to_relative_address();
+        let mut node = self.unary()?;
 
         loop {
-            node = if self.consume_reserve("*") {
-                Node::new_op2(Operator2::Mul, Box::new(node), Box::new(self.unary()?))
+            let span = self.peek_span();
+            let op = if self.consume_reserve("*") {
+                Operator2::Mul
             } else if self.consume_reserve("/") {
-                Node::new_op2(Operator2::Div, Box::new(node), Box::new(self.unary()?))
+                Operator2::Div
+            } else if self.consume_reserve("%") {
+                Operator2::Mod
             } else {
                 break;
-            }
+            };
+            let right = self.unary()?;
+            node = self.new_arith(op, node.array_access_to_relative_address(), right, span);
         }
 
         Ok(node)
@@ -183,12 +687,13 @@ impl TokenStream {
         let mut node = self.mul()?;
 
         loop {
+            let span = self.peek_span();
             if self.consume_reserve("+") {
                 let right = self.mul()?;
-                node = Node::new_op2(Operator2::Add, Box::new(node), Box::new(right))
+                node = self.new_add(node, right, span)?;
             } else if self.consume_reserve("-") {
                 let right = self.mul()?;
-                node = Node::new_op2(Operator2::Sub, Box::new(node), Box::new(right))
+                node = self.new_sub(node, right, span)?;
             } else {
                 break;
             }
@@ -197,22 +702,42 @@ impl TokenStream {
         Ok(node)
     }
 
-    pub fn relational(&mut self) -> ParseResult<Node> {
+    pub fn shift(&mut self) -> ParseResult<Node> {
         let mut node = self.add()?;
 
         loop {
+            let span = self.peek_span();
+            let op = if self.consume_reserve("<<") {
+                Operator2::Shl
+            } else if self.consume_reserve(">>") {
+                Operator2::Shr
+            } else {
+                break;
+            };
+            let right = self.add()?;
+            node = self.new_arith(op, node, right, span);
+        }
+
+        Ok(node)
+    }
+
+    pub fn relational(&mut self) -> ParseResult<Node> {
+        let mut node = self.shift()?;
+
+        loop {
+            let span = self.peek_span();
             if self.consume_reserve("<") {
-                let right = self.add()?;
-                node = Node::new_op2(Operator2::Lt, Box::new(node), Box::new(right))
+                let right = self.shift()?;
+                node = self.new_arith(Operator2::Lt, node, right, span)
             } else if self.consume_reserve("<=") {
-                let right = self.add()?;
-                node = Node::new_op2(Operator2::Lte, Box::new(node), Box::new(right))
+                let right = self.shift()?;
+                node = self.new_arith(Operator2::Lte, node, right, span)
             } else if self.consume_reserve(">") {
-                let right = self.add()?;
-                node = Node::new_op2(Operator2::Lt, Box::new(right), Box::new(node))
+                let right = self.shift()?;
+                node = self.new_arith(Operator2::Lt, right, node, span)
             } else if self.consume_reserve(">=") {
-                let right = self.add()?;
-                node = Node::new_op2(Operator2::Lte, Box::new(right), Box::new(node))
+                let right = self.shift()?;
+                node = self.new_arith(Operator2::Lte, right, node, span)
             } else {
                 break;
             }
@@ -225,12 +750,13 @@ impl TokenStream {
         let mut node = self.relational()?;
 
         loop {
+            let span = self.peek_span();
             if self.consume_reserve("==") {
                 let right = self.relational()?;
-                node = Node::new_op2(Operator2::Eq, Box::new(node), Box::new(right))
+                node = self.new_arith(Operator2::Eq, node, right, span)
             } else if self.consume_reserve("!=") {
                 let right = self.relational()?;
-                node = Node::new_op2(Operator2::Eq, Box::new(node), Box::new(right))
+                node = self.new_arith(Operator2::Ne, node, right, span)
             } else {
                 break;
             }
@@ -239,11 +765,153 @@ impl TokenStream {
         Ok(node)
     }
 
-    pub fn assign(&mut self) -> ParseResult<Node> {
+    pub fn bit_and(&mut self) -> ParseResult<Node> {
         let mut node = self.equality()?;
+
+        loop {
+            let span = self.peek_span();
+            if self.consume_reserve("&") {
+                let right = self.equality()?;
+                node = self.new_arith(Operator2::BitAnd, node, right, span)
+            } else {
+                break;
+            }
+        }
+
+        Ok(node)
+    }
+
+    pub fn bit_xor(&mut self) -> ParseResult<Node> {
+        let mut node = self.bit_and()?;
+
+        loop {
+            let span = self.peek_span();
+            if self.consume_reserve("^") {
+                let right = self.bit_and()?;
+                node = self.new_arith(Operator2::BitXor, node, right, span)
+            } else {
+                break;
+            }
+        }
+
+        Ok(node)
+    }
+
+    pub fn bit_or(&mut self) -> ParseResult<Node> {
+        let mut node = self.bit_xor()?;
+
+        loop {
+            let span = self.peek_span();
+            if self.consume_reserve("|") {
+                let right = self.bit_xor()?;
+                node = self.new_arith(Operator2::BitOr, node, right, span)
+            } else {
+                break;
+            }
+        }
+
+        Ok(node)
+    }
+
+    pub fn logical_and(&mut self) -> ParseResult<Node> {
+        let mut node = self.bit_or()?;
+
+        loop {
+            let span = self.peek_span();
+            if self.consume_reserve("&&") {
+                let right = self.bit_or()?;
+                node = Node::new(
+                    NodeKind::LogicalAnd {
+                        left: Box::new(node),
+                        right: Box::new(right),
+                    },
+                    span,
+                )
+            } else {
+                break;
+            }
+        }
+
+        Ok(node)
+    }
+
+    pub fn logical_or(&mut self) -> ParseResult<Node> {
+        let mut node = self.logical_and()?;
+
+        loop {
+            let span = self.peek_span();
+            if self.consume_reserve("||") {
+                let right = self.logical_and()?;
+                node = Node::new(
+                    NodeKind::LogicalOr {
+                        left: Box::new(node),
+                        right: Box::new(right),
+                    },
+                    span,
+                )
+            } else {
+                break;
+            }
+        }
+
+        Ok(node)
+    }
+
+    pub fn conditional(&mut self) -> ParseResult<Node> {
+        let node = self.logical_or()?;
+        let span = self.peek_span();
+        if self.consume_reserve("?") {
+            let then_expr = self.expr()?;
+            self.expect_reserve(":")?;
+            let else_expr = self.conditional()?;
+            let (then_expr, else_expr) = match (then_expr.declare_type(), else_expr.declare_type())
+            {
+                (Some(t), Some(e)) if t.is_integer() && e.is_integer() => {
+                    let ty = Type::common(&t, &e);
+                    (then_expr.cast(ty.clone()), else_expr.cast(ty))
+                }
+                _ => (then_expr, else_expr),
+            };
+            Ok(Node::new(
+                NodeKind::Conditional(IfElse::new(
+                    node.into(),
+                    then_expr.array_access_to_relative_address().into(),
+                    Some(else_expr.array_access_to_relative_address().into()),
+                )),
+                span,
+            ))
+        } else {
+            Ok(node)
+        }
+    }
+
+    pub fn assign(&mut self) -> ParseResult<Node> {
+        let node = self.conditional()?;
+        let span = self.peek_span();
         if self.consume_reserve("=") {
-            node = Node::new_assign(Box::new(node), Box::new(self.equality()?));
+            let right = self.assign()?;
+            return self.new_assign(node, right, span);
+        }
+
+        let compound_operators = [
+            ("+=", Operator2::Add),
+            ("-=", Operator2::Sub),
+            ("*=", Operator2::Mul),
+            ("/=", Operator2::Div),
+            ("%=", Operator2::Mod),
+            ("<<=", Operator2::Shl),
+            (">>=", Operator2::Shr),
+            ("&=", Operator2::BitAnd),
+            ("|=", Operator2::BitOr),
+            ("^=", Operator2::BitXor),
+        ];
+        for (reserved, op) in compound_operators {
+            if self.consume_reserve(reserved) {
+                let right = self.assign()?;
+                return self.new_compound_assign(op, node, right, span);
+            }
         }
+
         Ok(node)
     }
 
@@ -251,27 +919,237 @@ impl TokenStream {
         self.assign()
     }
 
+    /// 二項演算. 両辺が整数なら通常の算術型変換で共通の型にそろえる
+    ///
+    /// シフトは左辺を汎整数拡張した型で計算するので, 右辺もその型にそろえておく.
+    fn new_arith(&self, op: Operator2, left: Node, right: Node, span: Span) -> Node {
+        let (left, right) = match (left.declare_type(), right.declare_type()) {
+            (Some(l), Some(r)) if l.is_integer() && r.is_integer() => {
+                let ty = match op {
+                    Operator2::Shl | Operator2::Shr => l.promoted(),
+                    _ => Type::common(&l, &r),
+                };
+                (left.cast(ty.clone()), right.cast(ty))
+            }
+            _ => (left, right),
+        };
+        Node::new_op2(op, Box::new(left), Box::new(right), span)
+    }
+
+    /// ポインタ演算を考慮した `left + right`
+    fn new_add(&self, left: Node, right: Node, span: Span) -> ParseResult<Node> {
+        let left = left.array_access_to_relative_address();
+        let right = right.array_access_to_relative_address();
+        match (left.pointee_size(), right.pointee_size()) {
+            (None, None) => Ok(self.new_arith(Operator2::Add, left, right, span)),
+            (Some(size), None) => Ok(Node::new_op2(
+                Operator2::Add,
+                Box::new(left),
+                Box::new(right.scale(size, span)),
+                span,
+            )),
+            (None, Some(size)) => Ok(Node::new_op2(
+                Operator2::Add,
+                Box::new(right),
+                Box::new(left.scale(size, span)),
+                span,
+            )),
+            (Some(_), Some(_)) => Err(ParseError::InvalidOperands(
+                "ポインタ同士は足せません",
+                span,
+            )),
+        }
+    }
+
+    /// ポインタ演算を考慮した `left - right`
+    fn new_sub(&self, left: Node, right: Node, span: Span) -> ParseResult<Node> {
+        let left = left.array_access_to_relative_address();
+        let right = right.array_access_to_relative_address();
+        match (left.pointee_size(), right.pointee_size()) {
+            (None, None) => Ok(self.new_arith(Operator2::Sub, left, right, span)),
+            (Some(size), None) => Ok(Node::new_op2(
+                Operator2::Sub,
+                Box::new(left),
+                Box::new(right.scale(size, span)),
+                span,
+            )),
+            // ポインタ同士の差は要素数
+            (Some(size), Some(_)) => Ok(Node::new_op2(
+                Operator2::Div,
+                Box::new(Node::new_op2(
+                    Operator2::Sub,
+                    Box::new(left),
+                    Box::new(right),
+                    span,
+                )),
+                Box::new(Node::num(size as i64, span)),
+                span,
+            )),
+            (None, Some(_)) => Err(ParseError::InvalidOperands(
+                "整数からポインタは引けません",
+                span,
+            )),
+        }
+    }
+
+    /// `.` の後のメンバ名を読んで `base` のメンバを取り出す
+    fn new_member(&mut self, base: Node, span: Span) -> ParseResult<Node> {
+        let name = self.expect_ident()?;
+        let Some(Type::Struct(st)) = base.declare_type() else {
+            return Err(ParseError::NotStruct(span));
+        };
+        let member = st.member(&name).ok_or(ParseError::NoMember(name, span))?;
+        Ok(Node::new(NodeKind::Member(Box::new(base), member), span))
+    }
+
+    /// 実引数を仮引数の型に変換する. 仮引数の型が分からない引数は汎整数拡張だけ行う
+    fn convert_arguments(
+        &mut self,
+        name: &str,
+        function: &FunctionType,
+        args: Vec<Node>,
+        span: Span,
+    ) -> ParseResult<Vec<Node>> {
+        let Some(params) = &function.params else {
+            return Ok(args.into_iter().map(Node::promote).collect());
+        };
+        if args.len() < params.len() || (args.len() > params.len() && !function.variadic) {
+            return Err(ParseError::ArgumentCount(
+                name.to_string(),
+                params.len(),
+                function.variadic,
+                args.len(),
+                span,
+            ));
+        }
+
+        let mut converted = vec![];
+        for (i, arg) in args.into_iter().enumerate() {
+            let Some(param) = params.get(i) else {
+                converted.push(arg.promote());
+                continue;
+            };
+            let arg_span = arg.span();
+            let arg = match arg.convert_as_if_by_assignment(param)? {
+                Conversion::Converted(arg) => arg,
+                Conversion::IncompatiblePointer(arg, actual) => {
+                    self.warnings.push(ParseWarning::IncompatiblePointer(
+                        name.to_string(),
+                        i + 1,
+                        actual,
+                        param.clone(),
+                        arg_span,
+                    ));
+                    arg
+                }
+                Conversion::Invalid(actual) => {
+                    return Err(ParseError::ArgumentType(
+                        name.to_string(),
+                        i + 1,
+                        actual,
+                        param.clone(),
+                        arg_span,
+                    ))
+                }
+            };
+            converted.push(arg);
+        }
+        Ok(converted)
+    }
+
+    /// `left = right`. 右辺は引数と同じ規則で左辺の型に変換してから代入する
+    fn new_assign(&mut self, left: Node, right: Node, span: Span) -> ParseResult<Node> {
+        let right = match left.declare_type() {
+            // 配列への代入は左辺値でないことをコード生成で報告する
+            Some(ty) if !matches!(ty, Type::Array(..)) => {
+                match right.convert_as_if_by_assignment(&ty)? {
+                    Conversion::Converted(right) => right,
+                    Conversion::IncompatiblePointer(right, actual) => {
+                        self.warnings
+                            .push(ParseWarning::IncompatiblePointerAssign(actual, ty, span));
+                        right
+                    }
+                    Conversion::Invalid(actual) => {
+                        return Err(ParseError::AssignType(actual, ty, span))
+                    }
+                }
+            }
+            _ => right,
+        };
+        Ok(Node::new_assign(Box::new(left), Box::new(right), span))
+    }
+
+    /// `left op= right`. ポインタへの `+=` と `-=` は要素の大きさで `right` を掛ける
+    fn new_compound_assign(
+        &self,
+        op: Operator2,
+        left: Node,
+        right: Node,
+        span: Span,
+    ) -> ParseResult<Node> {
+        let right = match (&op, left.pointee_size()) {
+            (Operator2::Add | Operator2::Sub, Some(size)) => right.scale(size, span),
+            // `right` の型を演算する型にしておく
+            _ => match (left.declare_type(), right.declare_type()) {
+                (Some(l), Some(r)) if l.is_integer() && r.is_integer() => match op {
+                    Operator2::Shl | Operator2::Shr => right.cast(l.promoted()),
+                    _ => right.cast(Type::common(&l, &r)),
+                },
+                _ => right,
+            },
+        };
+        Ok(Node::new(
+            NodeKind::CompoundAssign {
+                op,
+                left: Box::new(left),
+                right: Box::new(right),
+            },
+            span,
+        ))
+    }
+
+    /// `x++` / `x--` は `(x += 1) - 1` / `(x -= 1) + 1` として扱う
+    ///
+    /// 戻す計算は汎整数拡張した後に行うので, 整数なら `x` の型に変換し直して桁あふれを戻す.
+    fn new_post_increment(&self, operand: Node, delta: i64, span: Span) -> ParseResult<Node> {
+        let ty = operand.declare_type();
+        let (op, inverse) = if delta > 0 {
+            (Operator2::Add, Operator2::Sub)
+        } else {
+            (Operator2::Sub, Operator2::Add)
+        };
+        let updated = self.new_compound_assign(op, operand, Node::num(1, span), span)?;
+        let value = match inverse {
+            Operator2::Sub => self.new_sub(updated, Node::num(1, span), span)?,
+            _ => self.new_add(updated, Node::num(1, span), span)?,
+        };
+        match ty {
+            Some(ty) if ty.is_integer() => Ok(Node::new(NodeKind::Cast(Box::new(value), ty), span)),
+            _ => Ok(value),
+        }
+    }
+
     pub fn statement(&mut self) -> ParseResult<Node> {
+        let span = self.peek_span();
         if self.consume_reserve("{") {
+            self.local_variables.enter_scope();
             let mut statements = vec![];
             while !self.consume_reserve("}") {
                 statements.push(self.statement()?);
             }
-            Ok(Node::Block(statements))
-        } else if let Some(ty) = self.consume_type() {
-            let mut ty = ty;
-            let name = self.expect_ident()?;
-
-            if self.consume_reserve("[") {
-                let num = self.expect_number()?;
-                self.expect_reserve("]")?;
-                ty = Type::Array(Box::new(ty), num as usize);
+            self.local_variables.leave_scope();
+            Ok(Node::new(NodeKind::Block(statements), span))
+        } else if self.consume_typedef() {
+            self.typedef_declaration()?;
+            Ok(Node::new(NodeKind::Block(vec![]), span))
+        } else if let Some(base) = self.consume_base_type()? {
+            // `struct S { ... };` のように型だけを宣言する
+            if self.consume_reserve(";") {
+                return Ok(Node::new(NodeKind::Block(vec![]), span));
             }
-
+            let node = self.declaration(base, span)?;
             self.expect_reserve(";")?;
-            let dv = DefineVariable::new(name, ty);
-            self.local_variables.assign_local_variable(&dv);
-            Ok(Node::DefineVariable(dv))
+            Ok(node)
         } else if self.consume_if() {
             self.expect_reserve("(")?;
             let cond = self.expr()?;
@@ -283,19 +1161,29 @@ impl TokenStream {
             } else {
                 None
             };
-            Ok(Node::IfElse(IfElse::new(
-                cond.into(),
-                then_statement.into(),
-                else_statement.map(Box::new),
-            )))
+            Ok(Node::new(
+                NodeKind::IfElse(IfElse::new(
+                    cond.into(),
+                    then_statement.into(),
+                    else_statement.map(Box::new),
+                )),
+                span,
+            ))
         } else if self.consume_for() {
             self.expect_reserve("(")?;
-            let init = if !self.consume_reserve(";") {
-                let init = self.expr()?;
+            // 初期化節で宣言した変数は for 文の終わりまで有効
+            self.local_variables.enter_scope();
+            let init_span = self.peek_span();
+            let init = if self.consume_reserve(";") {
+                None
+            } else if let Some(base) = self.consume_base_type()? {
+                let init = self.declaration(base, init_span)?;
                 self.expect_reserve(";")?;
                 Some(init)
             } else {
-                None
+                let init = self.expr()?;
+                self.expect_reserve(";")?;
+                Some(init)
             };
             let cond = if !self.consume_reserve(";") {
                 let cond = self.expr()?;
@@ -312,92 +1200,335 @@ impl TokenStream {
                 None
             };
 
-            let body = self.statement()?;
-
-            Ok(Node::For(For::new(
-                init.map(Box::new),
-                cond.map(Box::new),
-                next.map(Box::new),
-                body.into(),
-            )))
+            let body = self.loop_body()?;
+            self.local_variables.leave_scope();
+
+            Ok(Node::new(
+                NodeKind::For(For::new(
+                    init.map(Box::new),
+                    cond.map(Box::new),
+                    next.map(Box::new),
+                    body.into(),
+                )),
+                span,
+            ))
         } else if self.consume_while() {
             self.expect_reserve("(")?;
             let cond = self.expr()?;
             self.expect_reserve(")")?;
-            let body = self.statement()?;
-
-            Ok(Node::For(For::new(
-                None,
-                Some(cond.into()),
-                None,
-                body.into(),
-            )))
-        } else {
-            let is_return = self.consume_return();
+            let body = self.loop_body()?;
 
-            let node = self.expr()?;
+            Ok(Node::new(
+                NodeKind::For(For::new(None, Some(cond.into()), None, body.into())),
+                span,
+            ))
+        } else if self.consume_do() {
+            let body = self.loop_body()?;
+            if !self.consume_while() {
+                return Err(ParseError::ExpectReserved(
+                    "while".to_string(),
+                    self.inner.peek().unwrap().clone(),
+                ));
+            }
+            self.expect_reserve("(")?;
+            let cond = self.expr()?;
+            self.expect_reserve(")")?;
             self.expect_reserve(";")?;
-
-            if is_return {
-                Ok(Node::Return(node.into()))
-            } else {
-                Ok(node)
+            Ok(Node::new(
+                NodeKind::DoWhile(DoWhile::new(body.into(), cond.into())),
+                span,
+            ))
+        } else if self.consume_switch() {
+            self.expect_reserve("(")?;
+            let cond = self.expr()?.promote();
+            self.expect_reserve(")")?;
+            let ty = cond
+                .declare_type()
+                .ok_or(ParseError::UndeterminedType(span))?;
+            self.switches.push(Switch::new(cond.into(), ty));
+            let body = self.statement();
+            let mut switch = self.switches.pop().unwrap();
+            switch.body = Some(Box::new(body?));
+            Ok(Node::new(NodeKind::Switch(switch), span))
+        } else if self.consume_case() {
+            let value_span = self.peek_span();
+            let value = self
+                .conditional()?
+                .eval_constant()
+                .ok_or(ParseError::NotConstant(value_span))?;
+            self.expect_reserve(":")?;
+            let Some(switch) = self.switches.last_mut() else {
+                return Err(ParseError::OutsideSwitch("case", span));
+            };
+            // 制御式と同じ型で比べる
+            let value = switch.ty.convert_constant(value);
+            if switch.cases.contains(&value) {
+                return Err(ParseError::DuplicatedCase(value, value_span));
             }
+            switch.cases.push(value);
+            let index = switch.cases.len() - 1;
+            let statement = self.statement()?;
+            Ok(Node::new(NodeKind::Case(index, Box::new(statement)), span))
+        } else if self.consume_default() {
+            self.expect_reserve(":")?;
+            let Some(switch) = self.switches.last_mut() else {
+                return Err(ParseError::OutsideSwitch("default", span));
+            };
+            if switch.has_default {
+                return Err(ParseError::DuplicatedDefault(span));
+            }
+            switch.has_default = true;
+            let statement = self.statement()?;
+            Ok(Node::new(NodeKind::Default(Box::new(statement)), span))
+        } else if self.consume_break() {
+            if self.loop_depth == 0 && self.switches.is_empty() {
+                return Err(ParseError::BreakOutsideLoop(span));
+            }
+            self.expect_reserve(";")?;
+            Ok(Node::new(NodeKind::Break, span))
+        } else if self.consume_continue() {
+            if self.loop_depth == 0 {
+                return Err(ParseError::ContinueOutsideLoop(span));
+            }
+            self.expect_reserve(";")?;
+            Ok(Node::new(NodeKind::Continue, span))
+        } else if self.consume_goto() {
+            let label_span = self.peek_span();
+            let label = self.expect_ident()?;
+            self.expect_reserve(";")?;
+            self.gotos.push((label.clone(), label_span));
+            Ok(Node::new(NodeKind::Goto(label), span))
+        } else if self.at_label() {
+            let label = self.expect_ident()?;
+            self.expect_reserve(":")?;
+            if !self.labels.insert(label.clone()) {
+                return Err(ParseError::DuplicatedLabel(label, span));
+            }
+            let statement = self.statement()?;
+            Ok(Node::new(NodeKind::Label(label, Box::new(statement)), span))
+        } else {
+            if self.consume_return() {
+                return self.return_statement(span);
+            }
+
+            let node = self.expr()?;
+            self.expect_reserve(";")?;
+            Ok(node)
         }
     }
 
-    fn param_list(&mut self) -> ParseResult<Vec<Parameter>> {
-        let mut params = vec![];
+    /// ループの本体. この中では break と continue を書ける
+    fn loop_body(&mut self) -> ParseResult<Node> {
+        self.loop_depth += 1;
+        let body = self.statement();
+        self.loop_depth -= 1;
+        body
+    }
+
+    /// `識別子 :` で始まるラベル付きの文か
+    fn at_label(&self) -> bool {
+        matches!(
+            (self.inner.peek().map(Token::kind), self.inner.peek_nth(1).map(Token::kind)),
+            (Some(TokenKind::Ident(_)), Some(TokenKind::Reserved(colon))) if colon == ":"
+        )
+    }
+
+    /// `return` の後. 値は関数の戻り値の型に変換する
+    fn return_statement(&mut self, span: Span) -> ParseResult<Node> {
+        if self.consume_reserve(";") {
+            if self.return_type != Type::Void {
+                self.warnings.push(ParseWarning::MissingReturnValue(span));
+            }
+            return Ok(Node::new(NodeKind::Return(None), span));
+        }
+
+        let value = self.expr()?;
+        self.expect_reserve(";")?;
+        if self.return_type == Type::Void {
+            return Err(ParseError::ReturnValueInVoidFunction(span));
+        }
+        let value = match value.convert_as_if_by_assignment(&self.return_type)? {
+            Conversion::Converted(value) => value,
+            Conversion::IncompatiblePointer(value, actual) => {
+                self.warnings.push(ParseWarning::IncompatiblePointerReturn(
+                    actual,
+                    self.return_type.clone(),
+                    span,
+                ));
+                value
+            }
+            Conversion::Invalid(actual) => {
+                return Err(ParseError::ReturnType(
+                    actual,
+                    self.return_type.clone(),
+                    span,
+                ))
+            }
+        };
+        Ok(Node::new(NodeKind::Return(Some(Box::new(value))), span))
+    }
+
+    /// 仮引数の並び. `(void)` は仮引数なし, `...` は可変長引数
+    fn param_list(&mut self) -> ParseResult<ParamList> {
         self.expect_reserve("(")?;
-        if !self.consume_reserve(")") {
-            let ty = self.expect_type()?;
-            let name = self.expect_ident()?;
-            params.push(Parameter::new(name, ty));
-            while !self.consume_reserve(")") {
-                self.expect_reserve(",")?;
-                let ty = self.expect_type()?;
-                let name = self.expect_ident()?;
-                params.push(Parameter::new(name, ty));
+        let mut list = ParamList {
+            params: vec![],
+            unspecified: false,
+            variadic: false,
+        };
+        if self.consume_reserve(")") {
+            list.unspecified = true;
+            return Ok(list);
+        }
+        if matches!(self.peek_kind(), TokenKind::Void)
+            && matches!(self.inner.peek_nth(1).map(Token::kind), Some(TokenKind::Reserved(s)) if s == ")")
+        {
+            self.inner.next().unwrap();
+            self.inner.next().unwrap();
+            return Ok(list);
+        }
+
+        loop {
+            if self.consume_reserve("...") {
+                list.variadic = true;
+                self.expect_reserve(")")?;
+                return Ok(list);
+            }
+            let mut ty = self.expect_base_type()?;
+            while self.consume_reserve("*") {
+                ty = Type::Ptr(Box::new(ty));
             }
+            let name_span = self.peek_span();
+            let name = match self.consume_ident() {
+                Some(name) => Ok(name),
+                None => Err(self.inner.peek().unwrap().clone()),
+            };
+            // 配列の仮引数はポインタになる
+            let ty = self.array_dimensions(ty)?.decay();
+            list.params.push((name, name_span, ty));
+
+            if self.consume_reserve(")") {
+                return Ok(list);
+            }
+            self.expect_reserve(",")?;
         }
-        Ok(params)
     }
 
-    pub fn expect_define_function(&mut self) -> ParseResult<Node> {
-        self.expect_int()?;
-        let name = self.expect_ident()?;
-        self.global_symbols.insert(name.clone(), Some(Type::Int));
+    /// 型と名前を読んだ後の関数の宣言か定義. 宣言だけなら `None`
+    fn function(
+        &mut self,
+        return_type: Type,
+        name: String,
+        span: Span,
+    ) -> ParseResult<Option<Node>> {
+        let list = self.param_list()?;
+        let function = FunctionType {
+            return_type: return_type.clone(),
+            params: match list.unspecified {
+                true => None,
+                false => Some(list.params.iter().map(|(_, _, ty)| ty.clone()).collect()),
+            },
+            variadic: list.variadic,
+        };
+        match self.global_symbols.get(&name) {
+            Some(GlobalSymbol::Function(declared)) if !declared.is_compatible(&function) => {
+                return Err(ParseError::ConflictingTypes(name, span));
+            }
+            // 先に仮引数まで宣言されていれば, そちらで呼び出しを確かめる
+            Some(GlobalSymbol::Function(declared)) if function.params.is_none() => {}
+            Some(GlobalSymbol::Function(_)) | None => {
+                self.global_symbols
+                    .insert(name.clone(), GlobalSymbol::Function(function));
+            }
+            Some(_) => return Err(ParseError::ConflictingTypes(name, span)),
+        }
+
+        if self.consume_reserve(";") {
+            return Ok(None);
+        }
 
+        if !self.defined_functions.insert(name.clone()) {
+            return Err(ParseError::RedefinedFunction(name, span));
+        }
+        let mut params = vec![];
+        for (name, name_span, ty) in list.params {
+            let name = name.map_err(ParseError::ExpectIdent)?;
+            params.push((Parameter::new(name, ty), name_span));
+        }
+        self.define_function(name, return_type, params, span)
+            .map(Some)
+    }
+
+    /// 仮引数まで読んだ後の関数定義. 仮引数はそれぞれ名前の位置を持つ
+    fn define_function(
+        &mut self,
+        name: String,
+        return_type: Type,
+        params: Vec<(Parameter, Span)>,
+        span: Span,
+    ) -> ParseResult<Node> {
         self.local_variables.clear();
-        let params = self.param_list()?;
-        for p in &params {
+        self.return_type = return_type.clone();
+        // 仮引数と関数本体の一番外側のブロックは同じスコープ
+        self.local_variables.enter_scope();
+        // メモリで返す構造体の書き込み先は隠れた最初の引数として rbp-8 に置く
+        if return_type.is_returned_in_memory() {
             self.local_variables
-                .assign_local_variable(&DefineVariable::from(p.clone()));
+                .assign_temporary(&Type::Ptr(Box::new(return_type.clone())));
+        }
+        for (p, name_span) in &params {
+            if self
+                .local_variables
+                .assign_local_variable(&DefineVariable::from(p.clone()))
+                .is_none()
+            {
+                return Err(ParseError::DuplicatedVariable(
+                    p.name().to_string(),
+                    *name_span,
+                ));
+            }
         }
         self.expect_reserve("{")?;
         let mut statements = vec![];
         while !self.consume_reserve("}") {
             statements.push(self.statement()?);
         }
+        self.local_variables.leave_scope();
 
-        let node = Node::DefineFunction(DefineFunction::new(name.clone(), params, statements));
+        // goto はラベルより前に書けるので, 関数を読み終えてから飛び先を確かめる
+        let labels = std::mem::take(&mut self.labels);
+        for (label, label_span) in std::mem::take(&mut self.gotos) {
+            if !labels.contains(&label) {
+                return Err(ParseError::NotDefinedLabel(label, label_span));
+            }
+        }
+
+        let stack_size = self.local_variables.current_offset;
+        let node = Node::new(
+            NodeKind::DefineFunction(DefineFunction::new(
+                name.clone(),
+                return_type,
+                params.into_iter().map(|(p, _)| p).collect(),
+                statements,
+                stack_size,
+            )),
+            span,
+        );
 
         Ok(node)
     }
 
-    fn consume_int(&mut self) -> bool {
-        match self.inner.peek().unwrap() {
-            Token::Int => {
-                self.inner.next().unwrap();
-                true
-            }
-            _ => false,
-        }
+    fn peek_kind(&mut self) -> &TokenKind {
+        self.inner.peek().unwrap().kind()
+    }
+
+    fn peek_span(&mut self) -> Span {
+        self.inner.peek().unwrap().span()
     }
 
     fn consume_sizeof(&mut self) -> bool {
-        match self.inner.peek().unwrap() {
-            Token::Sizeof => {
+        match self.peek_kind() {
+            TokenKind::Sizeof => {
                 self.inner.next().unwrap();
                 true
             }
@@ -406,56 +1537,897 @@ impl TokenStream {
     }
 
     fn expect_type(&mut self) -> ParseResult<Type> {
-        self.expect_int()?;
+        match self.consume_type()? {
+            Some(ty) => Ok(ty),
+            None => Err(ParseError::ExpectType(self.inner.peek().unwrap().clone())),
+        }
+    }
 
-        let mut ty = Type::Int;
+    /// 型指定子の並びと `*` を読む. 型で始まっていなければ None
+    fn consume_type(&mut self) -> ParseResult<Option<Type>> {
+        let Some(mut ty) = self.consume_base_type()? else {
+            return Ok(None);
+        };
 
         while self.consume_reserve("*") {
             ty = Type::Ptr(Box::new(ty));
         }
 
+        Ok(Some(ty))
+    }
+
+    /// `unsigned long int` のような型指定子の並びを読んで整数型を決める
+    fn consume_base_type(&mut self) -> ParseResult<Option<Type>> {
+        let span = self.peek_span();
+        if let Some(kind) = self.consume_struct_keyword() {
+            return Ok(Some(self.struct_declaration(kind)?));
+        }
+        if self.consume_enum() {
+            return Ok(Some(self.enum_declaration()?));
+        }
+        if matches!(self.peek_kind(), TokenKind::Void) {
+            self.inner.next().unwrap();
+            return Ok(Some(Type::Void));
+        }
+        if let Some(ty) = self.typedef_name(self.inner.peek().unwrap()) {
+            self.inner.next().unwrap();
+            return Ok(Some(ty));
+        }
+
+        let (mut char, mut short, mut int, mut long) = (0, 0, 0, 0);
+        let (mut signed, mut unsigned) = (0, 0);
+        let mut any = false;
+
+        loop {
+            let counter = match self.peek_kind() {
+                TokenKind::Char => &mut char,
+                TokenKind::Short => &mut short,
+                TokenKind::Int => &mut int,
+                TokenKind::Long => &mut long,
+                TokenKind::Signed => &mut signed,
+                TokenKind::Unsigned => &mut unsigned,
+                _ => break,
+            };
+            *counter += 1;
+            any = true;
+            self.inner.next().unwrap();
+        }
+
+        if !any {
+            return Ok(None);
+        }
+
+        let is_unsigned = match (signed, unsigned) {
+            (0, 0) | (1, 0) => false,
+            (0, 1) => true,
+            _ => return Err(ParseError::InvalidTypeSpecifier(span)),
+        };
+        let ty = match (char, short, int, long, is_unsigned) {
+            (1, 0, 0, 0, false) => Type::Char,
+            (1, 0, 0, 0, true) => Type::UChar,
+            (0, 1, 0 | 1, 0, false) => Type::Short,
+            (0, 1, 0 | 1, 0, true) => Type::UShort,
+            (0, 0, 0 | 1, 0, false) => Type::Int,
+            (0, 0, 0 | 1, 0, true) => Type::UInt,
+            (0, 0, 0 | 1, 1, false) => Type::Long,
+            (0, 0, 0 | 1, 1, true) => Type::ULong,
+            (0, 0, 0 | 1, 2, false) => Type::LongLong,
+            (0, 0, 0 | 1, 2, true) => Type::ULongLong,
+            _ => return Err(ParseError::InvalidTypeSpecifier(span)),
+        };
+
+        Ok(Some(ty))
+    }
+
+    /// `typedef` で付けた名前なら, その型. 内側のスコープの変数などで隠されていれば `None`
+    fn typedef_name(&self, token: &Token) -> Option<Type> {
+        let TokenKind::Ident(name) = token.kind() else {
+            return None;
+        };
+        match self.local_variables.get(name) {
+            Some(LocalSymbol::Typedef(ty)) => Some(ty.clone()),
+            Some(_) => None,
+            None => match self.global_symbols.get(name) {
+                Some(GlobalSymbol::Typedef(ty)) => Some(ty.clone()),
+                _ => None,
+            },
+        }
+    }
+
+    /// 関数の中なら今のスコープ, 外ならファイルスコープに型の名前か列挙定数を宣言する
+    fn declare_symbol(&mut self, name: String, symbol: LocalSymbol, span: Span) -> ParseResult<()> {
+        if self.local_variables.in_function() {
+            if !self.local_variables.declare(&name, symbol) {
+                return Err(ParseError::DuplicatedVariable(name, span));
+            }
+            return Ok(());
+        }
+        let symbol = match symbol {
+            LocalSymbol::Typedef(ty) => GlobalSymbol::Typedef(ty),
+            LocalSymbol::EnumConstant(value) => GlobalSymbol::EnumConstant(value),
+            LocalSymbol::Variable(..) => unreachable!("ローカル変数はファイルスコープにありません"),
+        };
+        match (self.global_symbols.get(&name), &symbol) {
+            (None, _) => {}
+            // 同じ型の typedef は何度書いてもよい
+            (Some(GlobalSymbol::Typedef(declared)), GlobalSymbol::Typedef(ty))
+                if declared == ty => {}
+            _ => return Err(ParseError::DuplicatedVariable(name, span)),
+        }
+        self.global_symbols.insert(name, symbol);
+        Ok(())
+    }
+
+    /// 型を読んだ後のローカル変数の宣言. `,` で区切って複数の変数を宣言できる
+    fn declaration(&mut self, base: Type, span: Span) -> ParseResult<Node> {
+        let mut nodes = vec![];
+        loop {
+            self.init_declarator(base.clone(), span, &mut nodes)?;
+            if !self.consume_reserve(",") {
+                break;
+            }
+        }
+        if nodes.len() == 1 {
+            return Ok(nodes.pop().unwrap());
+        }
+        Ok(Node::new(NodeKind::Block(nodes), span))
+    }
+
+    /// 宣言子1つ分. 初期化子があれば各要素への代入を `nodes` に続ける
+    fn init_declarator(
+        &mut self,
+        base: Type,
+        span: Span,
+        nodes: &mut Vec<Node>,
+    ) -> ParseResult<()> {
+        let name_span = self.peek_span();
+        let (name, mut ty) = self.declarator(base)?;
+        let has_initializer = self.consume_reserve("=");
+        if let (Type::Array(_, 0), false) = (&ty, has_initializer) {
+            return Err(ParseError::InvalidInitializer(
+                "大きさを省略した配列には初期化子が必要です",
+                name_span,
+            ));
+        }
+
+        // 大きさを省略した配列は初期化子を先に読んで大きさを決める
+        let mut init = None;
+        if let (Type::Array(elem, 0), true) = (&ty, has_initializer) {
+            let init_span = self.peek_span();
+            let unsized_init = self.initializer(&ty)?;
+            if unsized_init.len() == 0 {
+                return Err(ParseError::InvalidInitializer(
+                    "初期化子が空です",
+                    init_span,
+                ));
+            }
+            ty = Type::Array(elem.clone(), unsized_init.len());
+            init = Some(unsized_init);
+        }
+
+        let dv = DefineVariable::new(name.clone(), ty.clone());
+        let Some(_) = self.local_variables.assign_local_variable(&dv) else {
+            return Err(ParseError::DuplicatedVariable(name, name_span));
+        };
+        nodes.push(Node::new(NodeKind::DefineVariable(dv), span));
+        if !has_initializer {
+            return Ok(());
+        }
+
+        let init = match init {
+            Some(init) => init,
+            None => self.initializer(&ty)?,
+        };
+        let Some(LocalSymbol::Variable(offset, _)) = self.local_variables.get(&name).cloned()
+        else {
+            unreachable!("宣言したばかりの変数です");
+        };
+        let variable = Node::new(
+            NodeKind::LocalVariable(LocalVariable::new(name, offset, ty.clone())),
+            name_span,
+        );
+        self.lower_initializer(variable, &ty, init, nodes)
+    }
+
+    /// `ty` 型の変数の初期化子. 配列と構造体は `{ ... }` で要素ごとに書く
+    fn initializer(&mut self, ty: &Type) -> ParseResult<Initializer> {
+        let span = self.peek_span();
+        match ty {
+            Type::Array(elem, len) => {
+                if matches!(elem.as_ref(), Type::Char | Type::UChar) {
+                    if let Some(mut bytes) = self.consume_str() {
+                        while let Some(next) = self.consume_str() {
+                            bytes.extend(next);
+                        }
+                        return Ok(Initializer::String(bytes, span));
+                    }
+                }
+                if !self.consume_reserve("{") {
+                    return Err(ParseError::InvalidInitializer(
+                        "配列の初期化子は { } で囲んでください",
+                        span,
+                    ));
+                }
+                // 大きさを省略した配列には要素をいくつでも書ける
+                let limit = if *len == 0 { usize::MAX } else { *len };
+                let types = std::iter::repeat_n(elem.as_ref().clone(), limit);
+                self.initializer_elements(types, span)
+            }
+            Type::Struct(st) if self.consume_reserve("{") => {
+                // 共用体は最初のメンバだけを初期化する
+                let members = st.members();
+                let count = match st.kind() {
+                    StructKind::Struct => members.len(),
+                    StructKind::Union => 1,
+                };
+                self.initializer_elements(members.into_iter().map(|m| m.ty).take(count), span)
+            }
+            // int x = {3}; のようにスカラも 1 つだけなら波括弧で囲める
+            _ if self.consume_reserve("{") => {
+                if self.consume_reserve("}") {
+                    return Err(ParseError::InvalidInitializer("初期化子が空です", span));
+                }
+                let init = self.initializer(ty)?;
+                self.consume_reserve(",");
+                if !self.consume_reserve("}") {
+                    return Err(ParseError::InvalidInitializer("初期化子が多すぎます", span));
+                }
+                Ok(init)
+            }
+            _ => Ok(Initializer::Expr(self.assign()?)),
+        }
+    }
+
+    /// `{` の後の初期化子の並びを `}` まで読む
+    fn initializer_elements(
+        &mut self,
+        mut types: impl Iterator<Item = Type>,
+        span: Span,
+    ) -> ParseResult<Initializer> {
+        let mut elements = vec![];
+        while !self.consume_reserve("}") {
+            if !elements.is_empty() {
+                self.expect_reserve(",")?;
+                if self.consume_reserve("}") {
+                    break;
+                }
+            }
+            let Some(ty) = types.next() else {
+                return Err(ParseError::InvalidInitializer("初期化子が多すぎます", span));
+            };
+            elements.push(self.element_initializer(&ty, None)?);
+        }
+        Ok(Initializer::List(elements, span))
+    }
+
+    /// 並びの中の要素 1 つ分の初期化子
+    ///
+    /// 配列や構造体の要素が `{` で始まらなければ波括弧が省略されているので,
+    /// 囲む並びから要素の数だけ読む. 構造体は同じ型の式でも初期化できるので,
+    /// 式を読んでみて型が違えば最初のメンバの初期化子 `first` として使う.
+    fn element_initializer(&mut self, ty: &Type, first: Option<Node>) -> ParseResult<Initializer> {
+        let span = first.as_ref().map_or_else(|| self.peek_span(), Node::span);
+        let first = match (ty, first) {
+            (_, None) if matches!(self.peek_kind(), TokenKind::Reserved(s) if s == "{") => {
+                return self.initializer(ty);
+            }
+            (Type::Array(elem, _), None)
+                if matches!(elem.as_ref(), Type::Char | Type::UChar)
+                    && matches!(self.peek_kind(), TokenKind::Str(_)) =>
+            {
+                return self.initializer(ty);
+            }
+            (Type::Array(elem, _), Some(node))
+                if matches!(elem.as_ref(), Type::Char | Type::UChar) =>
+            {
+                match node.kind() {
+                    NodeKind::StringLiteral(bytes) => {
+                        return Ok(Initializer::String(bytes.clone(), node.span()))
+                    }
+                    _ => Some(node),
+                }
+            }
+            (Type::Struct(_), first) => {
+                let node = match first {
+                    Some(node) => node,
+                    None => self.assign()?,
+                };
+                if node.declare_type().as_ref() == Some(ty) {
+                    return Ok(Initializer::Expr(node));
+                }
+                Some(node)
+            }
+            (Type::Array(..), first) => first,
+            (_, Some(node)) => return Ok(Initializer::Expr(node)),
+            (_, None) => return Ok(Initializer::Expr(self.assign()?)),
+        };
+
+        let mut types: Box<dyn Iterator<Item = Type>> = match ty {
+            Type::Array(elem, len) => Box::new(std::iter::repeat_n(elem.as_ref().clone(), *len)),
+            Type::Struct(st) => {
+                let members = st.members();
+                let count = match st.kind() {
+                    StructKind::Struct => members.len(),
+                    StructKind::Union => 1,
+                };
+                Box::new(members.into_iter().map(|m| m.ty).take(count))
+            }
+            _ => unreachable!("配列と構造体以外は先に返しています"),
+        };
+        let mut first = first;
+        let mut elements = vec![];
+        let mut next = types.next();
+        while let Some(element_type) = next {
+            elements.push(self.element_initializer(&element_type, first.take())?);
+            next = types.next();
+            // 次の `,` の後が `}` なら囲む並びの終わり
+            let more = matches!(self.peek_kind(), TokenKind::Reserved(s) if s == ",")
+                && !matches!(self.inner.peek_nth(1).map(Token::kind), Some(TokenKind::Reserved(s)) if s == "}");
+            if next.is_none() || !more {
+                break;
+            }
+            self.expect_reserve(",")?;
+        }
+        Ok(Initializer::List(elements, span))
+    }
+
+    /// 初期化子を `target` への代入の並びにする. 初期化子のない要素は 0 にする
+    fn lower_initializer(
+        &mut self,
+        target: Node,
+        ty: &Type,
+        init: Initializer,
+        nodes: &mut Vec<Node>,
+    ) -> ParseResult<()> {
+        match (ty, init) {
+            (Type::Array(_, len), Initializer::String(mut bytes, span)) => {
+                // char s[3] = "abc"; のように終端の 0 が入らないのは許される
+                if bytes.len() > *len {
+                    return Err(ParseError::InvalidInitializer(
+                        "初期化子の文字列が長すぎます",
+                        span,
+                    ));
+                }
+                bytes.resize(*len, 0);
+                for (i, byte) in bytes.into_iter().enumerate() {
+                    let element = self.new_index(target.clone(), i, span)?;
+                    nodes.push(self.new_assign(element, Node::num(byte as i64, span), span)?);
+                }
+            }
+            (Type::Array(elem, len), Initializer::List(elements, span)) => {
+                let mut elements = elements.into_iter();
+                for i in 0..*len {
+                    let element = self.new_index(target.clone(), i, span)?;
+                    match elements.next() {
+                        Some(init) => self.lower_initializer(element, elem, init, nodes)?,
+                        None => self.zero_initializer(element, elem, nodes)?,
+                    }
+                }
+            }
+            (Type::Struct(st), Initializer::List(elements, span)) => {
+                let mut elements = elements.into_iter();
+                for member in st.members() {
+                    let ty = member.ty.clone();
+                    let element =
+                        Node::new(NodeKind::Member(Box::new(target.clone()), member), span);
+                    match elements.next() {
+                        Some(init) => self.lower_initializer(element, &ty, init, nodes)?,
+                        None if st.kind() == StructKind::Struct => {
+                            self.zero_initializer(element, &ty, nodes)?
+                        }
+                        None => {}
+                    }
+                }
+            }
+            (_, Initializer::Expr(value)) => {
+                let span = value.span();
+                nodes.push(self.new_assign(target, value, span)?);
+            }
+            (_, Initializer::String(_, span) | Initializer::List(_, span)) => {
+                return Err(ParseError::InvalidInitializer(
+                    "初期化子の形が型と合いません",
+                    span,
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// `target` の全ての要素に 0 を代入する
+    fn zero_initializer(
+        &mut self,
+        target: Node,
+        ty: &Type,
+        nodes: &mut Vec<Node>,
+    ) -> ParseResult<()> {
+        let span = target.span();
+        match ty {
+            Type::Array(elem, len) => {
+                for i in 0..*len {
+                    let element = self.new_index(target.clone(), i, span)?;
+                    self.zero_initializer(element, elem, nodes)?;
+                }
+            }
+            Type::Struct(st) => {
+                // 共用体は最初のメンバを 0 にする
+                let members = st.members();
+                let count = match st.kind() {
+                    StructKind::Struct => members.len(),
+                    StructKind::Union => 1,
+                };
+                for member in members.into_iter().take(count) {
+                    let ty = member.ty.clone();
+                    let element =
+                        Node::new(NodeKind::Member(Box::new(target.clone()), member), span);
+                    self.zero_initializer(element, &ty, nodes)?;
+                }
+            }
+            _ => nodes.push(self.new_assign(target, Node::num(0, span), span)?),
+        }
+        Ok(())
+    }
+
+    /// `target[index]`
+    fn new_index(&self, target: Node, index: usize, span: Span) -> ParseResult<Node> {
+        let address = self.new_add(target, Node::num(index as i64, span), span)?;
+        Ok(Node::new(NodeKind::Deref(Box::new(address)), span))
+    }
+
+    /// `(` の次が型名ならキャストか `sizeof(型名)`
+    fn at_type_name_in_parens(&self) -> bool {
+        let (Some(paren), Some(next)) = (self.inner.peek(), self.inner.peek_nth(1)) else {
+            return false;
+        };
+        if !matches!(paren.kind(), TokenKind::Reserved(s) if s == "(") {
+            return false;
+        }
+        matches!(
+            next.kind(),
+            TokenKind::Void
+                | TokenKind::Char
+                | TokenKind::Short
+                | TokenKind::Int
+                | TokenKind::Long
+                | TokenKind::Signed
+                | TokenKind::Unsigned
+                | TokenKind::Struct
+                | TokenKind::Union
+                | TokenKind::Enum
+        ) || self.typedef_name(next).is_some()
+    }
+
+    /// キャストや `sizeof` に書く型名. 名前のない宣言子として `*` と配列の大きさを読む
+    fn type_name(&mut self) -> ParseResult<Type> {
+        let ty = self.expect_type()?;
+        self.array_dimensions(ty)
+    }
+
+    /// 配列の大きさを表す整数定数式
+    fn array_length(&mut self) -> ParseResult<usize> {
+        let span = self.peek_span();
+        let len = self
+            .conditional()?
+            .eval_constant()
+            .ok_or(ParseError::NotConstant(span))?;
+        usize::try_from(len)
+            .map_err(|_| ParseError::InvalidArrayLength("配列の大きさが負です", span))
+    }
+
+    /// `typedef` の後の型と名前を読んで型の名前を登録する
+    fn typedef_declaration(&mut self) -> ParseResult<()> {
+        let base = self.expect_base_type()?;
+        loop {
+            let span = self.peek_span();
+            let (name, ty) = self.declarator(base.clone())?;
+            self.declare_symbol(name, LocalSymbol::Typedef(ty), span)?;
+            if !self.consume_reserve(",") {
+                break;
+            }
+        }
+        self.expect_reserve(";")
+    }
+
+    /// `enum` の後のタグと列挙子を読む. 列挙型は int として扱う
+    fn enum_declaration(&mut self) -> ParseResult<Type> {
+        // タグは列挙型を区別しないので読み飛ばす
+        let tag = self.consume_ident();
+        if tag.is_some() && !self.consume_reserve("{") {
+            return Ok(Type::Int);
+        }
+        if tag.is_none() {
+            self.expect_reserve("{")?;
+        }
+
+        let mut value = 0;
+        while !self.consume_reserve("}") {
+            let name_span = self.peek_span();
+            let name = self.expect_ident()?;
+            if self.consume_reserve("=") {
+                let span = self.peek_span();
+                value = self
+                    .conditional()?
+                    .eval_constant()
+                    .ok_or(ParseError::NotConstant(span))?;
+            }
+            self.declare_symbol(name, LocalSymbol::EnumConstant(value), name_span)?;
+            value += 1;
+
+            if !self.consume_reserve(",") {
+                self.expect_reserve("}")?;
+                break;
+            }
+        }
+
+        Ok(Type::Int)
+    }
+
+    /// `*` の並び, 名前, 配列の大きさを読んで宣言する名前と型を返す
+    fn declarator(&mut self, base: Type) -> ParseResult<(String, Type)> {
+        let mut ty = base;
+        while self.consume_reserve("*") {
+            ty = Type::Ptr(Box::new(ty));
+        }
+        let name = self.expect_ident()?;
+        Ok((name, self.array_dimensions(ty)?))
+    }
+
+    /// 宣言子の後に続く `[N]` の並びを読んで配列型にする
+    fn array_dimensions(&mut self, base: Type) -> ParseResult<Type> {
+        let mut ty = base;
+        let mut lengths = vec![];
+        while self.consume_reserve("[") {
+            let span = self.peek_span();
+            // 大きさを省略した配列は初期化子で大きさが決まるまで 0 にしておく
+            if self.consume_reserve("]") {
+                lengths.push((0, span));
+                continue;
+            }
+            lengths.push((self.array_length()?, span));
+            self.expect_reserve("]")?;
+        }
+        // int a[2][3] は「int[3] の 2 要素の配列」
+        for (len, span) in lengths.into_iter().rev() {
+            ty = Type::Array(Box::new(ty), len);
+            // 大きさはポインタの差と同じく符号付きで表せる範囲に収める
+            if ty
+                .checked_size()
+                .is_none_or(|size| size > isize::MAX as usize)
+            {
+                return Err(ParseError::InvalidArrayLength("配列が大きすぎます", span));
+            }
+        }
         Ok(ty)
     }
 
-    fn consume_type(&mut self) -> Option<Type> {
-        match self.inner.peek().unwrap() {
-            Token::Int => {
+    fn consume_enum(&mut self) -> bool {
+        match self.peek_kind() {
+            TokenKind::Enum => {
                 self.inner.next().unwrap();
-                let mut ty = Type::Int;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn consume_typedef(&mut self) -> bool {
+        match self.peek_kind() {
+            TokenKind::Typedef => {
+                self.inner.next().unwrap();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn consume_struct_keyword(&mut self) -> Option<StructKind> {
+        let kind = match self.peek_kind() {
+            TokenKind::Struct => StructKind::Struct,
+            TokenKind::Union => StructKind::Union,
+            _ => return None,
+        };
+        self.inner.next().unwrap();
+        Some(kind)
+    }
 
-                while self.consume_reserve("*") {
-                    ty = Type::Ptr(Box::new(ty));
+    /// `struct` / `union` の後のタグとメンバの宣言を読む
+    fn struct_declaration(&mut self, kind: StructKind) -> ParseResult<Type> {
+        let span = self.peek_span();
+        let tag = self.consume_ident();
+
+        if !self.consume_reserve("{") {
+            let Some(tag) = tag else {
+                return Err(ParseError::ExpectReserved(
+                    "{".to_string(),
+                    self.inner.peek().unwrap().clone(),
+                ));
+            };
+            // 宣言済みの型の参照, または前方宣言
+            if let Some(st) = self.find_tag(&tag) {
+                return Ok(Type::Struct(st));
+            }
+            let st = StructType::new(kind, Some(tag.clone()));
+            self.declare_tag(tag, st.clone());
+            return Ok(Type::Struct(st));
+        }
+
+        // 外側のスコープのタグは内側で定義し直して隠せる
+        let st = match &tag {
+            Some(tag) => match self.find_tag_in_current_scope(tag) {
+                Some(st) if st.is_complete() => {
+                    return Err(ParseError::RedefinedStruct(tag.clone(), span));
                 }
+                Some(st) => st,
+                None => {
+                    let st = StructType::new(kind, Some(tag.clone()));
+                    // メンバから自分自身へのポインタを使えるように先に登録する
+                    self.declare_tag(tag.clone(), st.clone());
+                    st
+                }
+            },
+            None => StructType::new(kind, None),
+        };
 
-                Some(ty)
+        let mut members = vec![];
+        while !self.consume_reserve("}") {
+            let base = self.expect_base_type()?;
+            // 名前のない構造体や共用体のメンバは外側のメンバとして扱う
+            if self.consume_reserve(";") {
+                members.push((None, base));
+                continue;
             }
-            _ => None,
+            loop {
+                let span = self.peek_span();
+                let (name, ty) = self.declarator(base.clone())?;
+                if ty.is_incomplete() {
+                    return Err(ParseError::IncompleteMember(name, span));
+                }
+                members.push((Some(name), ty));
+                if !self.consume_reserve(",") {
+                    break;
+                }
+            }
+            self.expect_reserve(";")?;
         }
+        st.define(members);
+
+        Ok(Type::Struct(st))
     }
 
-    fn expect_int(&mut self) -> ParseResult<()> {
-        match self.inner.peek().unwrap() {
-            Token::Int => {
-                self.inner.next().unwrap();
-                Ok(())
+    /// 内側のスコープから順にタグを探す. 関数の外ならファイルスコープだけを見る
+    fn find_tag(&self, tag: &str) -> Option<StructType> {
+        self.local_variables
+            .get_tag(tag)
+            .or_else(|| self.struct_tags.get(tag))
+            .cloned()
+    }
+
+    /// 今のスコープで宣言したタグ
+    fn find_tag_in_current_scope(&self, tag: &str) -> Option<StructType> {
+        if self.local_variables.in_function() {
+            self.local_variables.get_tag_in_current_scope(tag).cloned()
+        } else {
+            self.struct_tags.get(tag).cloned()
+        }
+    }
+
+    /// 関数の中なら今のスコープ, 外ならファイルスコープにタグを宣言する
+    fn declare_tag(&mut self, tag: String, st: StructType) {
+        if self.local_variables.in_function() {
+            self.local_variables.declare_tag(tag, st);
+        } else {
+            self.struct_tags.insert(tag, st);
+        }
+    }
+
+    fn expect_base_type(&mut self) -> ParseResult<Type> {
+        match self.consume_base_type()? {
+            Some(ty) => Ok(ty),
+            None => Err(ParseError::ExpectType(self.inner.peek().unwrap().clone())),
+        }
+    }
+
+    /// 最初の宣言子を読んだ後のグローバル変数の宣言. `,` で区切って複数の変数を宣言できる
+    fn global_declaration(
+        &mut self,
+        base: Type,
+        name: String,
+        ty: Type,
+        span: Span,
+    ) -> ParseResult<Vec<Node>> {
+        let mut nodes: Vec<Node> = self
+            .define_global_variable(name, ty, span)?
+            .into_iter()
+            .collect();
+        while self.consume_reserve(",") {
+            let span = self.peek_span();
+            let (name, ty) = self.declarator(base.clone())?;
+            nodes.extend(self.define_global_variable(name, ty, span)?);
+        }
+        self.expect_reserve(";")?;
+        Ok(nodes)
+    }
+
+    /// 宣言子1つ分のグローバル変数. 初期化子はローカル変数と同じ形で読むが, 値は定数式に限る
+    ///
+    /// 初期化子のない定義は同じ型なら何度書いてもよいので, ファイルの最後まで出力を待つ.
+    fn define_global_variable(
+        &mut self,
+        name: String,
+        ty: Type,
+        span: Span,
+    ) -> ParseResult<Option<Node>> {
+        // 関数の外の式から直前の関数のローカル変数が見えないようにする
+        self.local_variables.clear();
+
+        let mut ty = ty;
+        let init = if self.consume_reserve("=") {
+            let init_span = self.peek_span();
+            let init = self.initializer(&ty)?;
+            // 大きさを省略した配列は初期化子で大きさが決まる
+            if let Type::Array(elem, 0) = &ty {
+                if init.len() == 0 {
+                    return Err(ParseError::InvalidInitializer(
+                        "初期化子が空です",
+                        init_span,
+                    ));
+                }
+                ty = Type::Array(elem.clone(), init.len());
+            }
+            let mut values = vec![];
+            self.static_initializer(&ty, init, &mut values)?;
+            Some(values)
+        } else if let Type::Array(_, 0) = ty {
+            return Err(ParseError::InvalidInitializer(
+                "配列の大きさが決まりません",
+                span,
+            ));
+        } else {
+            None
+        };
+
+        match self.global_symbols.get(&name) {
+            Some(GlobalSymbol::Variable(declared)) if *declared != ty => {
+                return Err(ParseError::ConflictingTypes(name, span))
+            }
+            Some(GlobalSymbol::Variable(_)) if init.is_none() => return Ok(None),
+            Some(GlobalSymbol::Variable(_)) if self.defined_variables.contains(&name) => {
+                return Err(ParseError::RedefinedVariable(name, span))
             }
-            t => Err(ParseError::ExpectInt(t.clone())),
+            Some(GlobalSymbol::Variable(_)) | None => {}
+            Some(_) => return Err(ParseError::ConflictingTypes(name, span)),
+        }
+        self.global_symbols
+            .insert(name.clone(), GlobalSymbol::Variable(ty.clone()));
+
+        let initialized = init.is_some();
+        if initialized {
+            self.defined_variables.insert(name.clone());
+        }
+        let node = Node::new(
+            NodeKind::DefineGlobalVariable(DefineGlobalVariable::new(name, ty, init)),
+            span,
+        );
+        if initialized {
+            Ok(Some(node))
+        } else {
+            self.tentative_definitions.push(node);
+            Ok(None)
         }
     }
 
+    /// 初期化子を `ty` 型の静的な初期値の並びにする. 初期化子のない要素や詰め物は 0 にする
+    fn static_initializer(
+        &self,
+        ty: &Type,
+        init: Initializer,
+        values: &mut Vec<StaticValue>,
+    ) -> ParseResult<()> {
+        match (ty, init) {
+            (Type::Array(_, len), Initializer::String(mut bytes, span)) => {
+                // char s[3] = "abc"; のように終端の 0 が入らないのは許される
+                if bytes.len() > *len {
+                    return Err(ParseError::InvalidInitializer(
+                        "初期化子の文字列が長すぎます",
+                        span,
+                    ));
+                }
+                bytes.resize(*len, 0);
+                values.extend(bytes.into_iter().map(|b| StaticValue::Int(b as i64, 1)));
+            }
+            (Type::Array(elem, len), Initializer::List(elements, _)) => {
+                let count = elements.len();
+                for init in elements {
+                    self.static_initializer(elem, init, values)?;
+                }
+                if count < *len {
+                    values.push(StaticValue::Zero((len - count) * elem.size()));
+                }
+            }
+            (Type::Struct(st), Initializer::List(elements, _)) => {
+                let mut position = 0;
+                for (member, init) in st.members().into_iter().zip(elements) {
+                    if position < member.offset() {
+                        values.push(StaticValue::Zero(member.offset() - position));
+                    }
+                    self.static_initializer(&member.ty, init, values)?;
+                    position = member.offset() + member.ty.size();
+                }
+                if position < st.size() {
+                    values.push(StaticValue::Zero(st.size() - position));
+                }
+            }
+            (Type::Ptr(_), Initializer::Expr(node)) => {
+                if let Some(value) = node.eval_constant() {
+                    values.push(StaticValue::Int(value, ty.size()));
+                } else if let Some((target, offset)) = node.eval_address() {
+                    values.push(StaticValue::Address(target, offset));
+                } else {
+                    return Err(ParseError::NotConstant(node.span()));
+                }
+            }
+            (_, Initializer::Expr(node)) => {
+                let value = node
+                    .eval_constant()
+                    .ok_or(ParseError::NotConstant(node.span()))?;
+                values.push(StaticValue::Int(ty.convert_constant(value), ty.size()));
+            }
+            (_, Initializer::String(_, span) | Initializer::List(_, span)) => {
+                return Err(ParseError::InvalidInitializer(
+                    "初期化子の形が型と合いません",
+                    span,
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// 関数定義かグローバル変数の宣言
+    ///
+    /// `struct S { ... };` のように型だけを宣言した場合は空
+    fn toplevel(&mut self) -> ParseResult<Vec<Node>> {
+        let span = self.peek_span();
+        if self.consume_typedef() {
+            self.typedef_declaration()?;
+            return Ok(vec![]);
+        }
+        let base = self.expect_base_type()?;
+        if self.consume_reserve(";") {
+            return Ok(vec![]);
+        }
+        let (name, ty) = self.declarator(base.clone())?;
+
+        if matches!(self.peek_kind(), TokenKind::Reserved(s) if s == "(") {
+            Ok(self.function(ty, name, span)?.into_iter().collect())
+        } else {
+            self.global_declaration(base, name, ty, span)
+        }
+    }
+
+    /// これまでに見つかった警告
+    pub fn warnings(&self) -> &[ParseWarning] {
+        &self.warnings
+    }
+
     pub fn program(&mut self) -> ParseResult<Vec<Node>> {
-        let mut define_functions = vec![];
+        let mut nodes = vec![];
 
         while !self.at_eof() {
-            define_functions.push(self.expect_define_function()?);
+            nodes.extend(self.toplevel()?);
+        }
+        for node in std::mem::take(&mut self.tentative_definitions) {
+            match node.kind() {
+                NodeKind::DefineGlobalVariable(var)
+                    if self.defined_variables.contains(var.name()) => {}
+                _ => nodes.push(node),
+            }
         }
 
-        Ok(define_functions)
+        Ok(nodes)
     }
 
     fn consume_ident(&mut self) -> Option<String> {
-        match self.inner.peek().unwrap() {
-            Token::Ident(n) => {
+        match self.peek_kind() {
+            TokenKind::Ident(n) => {
                 let n = n.clone();
                 self.inner.next().unwrap();
                 Some(n)
@@ -464,20 +2436,31 @@ impl TokenStream {
         }
     }
 
+    fn consume_str(&mut self) -> Option<Vec<u8>> {
+        match self.peek_kind() {
+            TokenKind::Str(bytes) => {
+                let bytes = bytes.clone();
+                self.inner.next().unwrap();
+                Some(bytes)
+            }
+            _ => None,
+        }
+    }
+
     fn expect_ident(&mut self) -> ParseResult<String> {
-        match self.inner.peek().unwrap() {
-            Token::Ident(n) => {
+        match self.peek_kind() {
+            TokenKind::Ident(n) => {
                 let n = n.clone();
                 self.inner.next().unwrap();
                 Ok(n)
             }
-            _ => Err(ParseError::ExpectIdent),
+            _ => Err(ParseError::ExpectIdent(self.inner.peek().unwrap().clone())),
         }
     }
 
     fn consume_if(&mut self) -> bool {
-        match self.inner.peek().unwrap() {
-            Token::If => {
+        match self.peek_kind() {
+            TokenKind::If => {
                 self.inner.next().unwrap();
                 true
             }
@@ -486,8 +2469,78 @@ impl TokenStream {
     }
 
     fn consume_else(&mut self) -> bool {
-        match self.inner.peek().unwrap() {
-            Token::Else => {
+        match self.peek_kind() {
+            TokenKind::Else => {
+                self.inner.next().unwrap();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn consume_for(&mut self) -> bool {
+        match self.peek_kind() {
+            TokenKind::For => {
+                self.inner.next().unwrap();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn consume_while(&mut self) -> bool {
+        match self.peek_kind() {
+            TokenKind::While => {
+                self.inner.next().unwrap();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn consume_do(&mut self) -> bool {
+        match self.peek_kind() {
+            TokenKind::Do => {
+                self.inner.next().unwrap();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn consume_break(&mut self) -> bool {
+        match self.peek_kind() {
+            TokenKind::Break => {
+                self.inner.next().unwrap();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn consume_continue(&mut self) -> bool {
+        match self.peek_kind() {
+            TokenKind::Continue => {
+                self.inner.next().unwrap();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn consume_switch(&mut self) -> bool {
+        match self.peek_kind() {
+            TokenKind::Switch => {
+                self.inner.next().unwrap();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn consume_case(&mut self) -> bool {
+        match self.peek_kind() {
+            TokenKind::Case => {
                 self.inner.next().unwrap();
                 true
             }
@@ -495,9 +2548,9 @@ impl TokenStream {
         }
     }
 
-    fn consume_for(&mut self) -> bool {
-        match self.inner.peek().unwrap() {
-            Token::For => {
+    fn consume_default(&mut self) -> bool {
+        match self.peek_kind() {
+            TokenKind::Default => {
                 self.inner.next().unwrap();
                 true
             }
@@ -505,9 +2558,9 @@ impl TokenStream {
         }
     }
 
-    fn consume_while(&mut self) -> bool {
-        match self.inner.peek().unwrap() {
-            Token::While => {
+    fn consume_goto(&mut self) -> bool {
+        match self.peek_kind() {
+            TokenKind::Goto => {
                 self.inner.next().unwrap();
                 true
             }
@@ -516,8 +2569,8 @@ impl TokenStream {
     }
 
     fn consume_return(&mut self) -> bool {
-        match self.inner.peek().unwrap() {
-            Token::Return => {
+        match self.peek_kind() {
+            TokenKind::Return => {
                 self.inner.next().unwrap();
                 true
             }
@@ -526,8 +2579,8 @@ impl TokenStream {
     }
 
     pub fn consume_reserve(&mut self, op: &str) -> bool {
-        match self.inner.peek().unwrap() {
-            Token::Reserved(s) if s.as_str() == op => {
+        match self.peek_kind() {
+            TokenKind::Reserved(s) if s.as_str() == op => {
                 self.inner.next().unwrap();
                 true
             }
@@ -536,24 +2589,31 @@ impl TokenStream {
     }
 
     pub fn expect_reserve(&mut self, op: &str) -> ParseResult<()> {
-        match self.inner.next().unwrap() {
-            Token::Reserved(s) if s.as_str() == op => Ok(()),
-            _ => Err(ParseError::ExpectReserved(op.to_string())),
+        match self.peek_kind() {
+            TokenKind::Reserved(s) if s.as_str() == op => {
+                self.inner.next().unwrap();
+                Ok(())
+            }
+            _ => Err(ParseError::ExpectReserved(
+                op.to_string(),
+                self.inner.peek().unwrap().clone(),
+            )),
         }
     }
 
-    pub fn expect_number(&mut self) -> ParseResult<i64> {
-        match self.inner.next().unwrap() {
-            Token::Num(n) => Ok(n),
-            _ => return Err(ParseError::ExpectNumber),
+    pub fn expect_number(&mut self) -> ParseResult<(i64, Type)> {
+        match self.peek_kind() {
+            TokenKind::Num(n, ty) => {
+                let n = (*n, ty.clone());
+                self.inner.next().unwrap();
+                Ok(n)
+            }
+            _ => Err(ParseError::ExpectNumber(self.inner.peek().unwrap().clone())),
         }
     }
 
     pub fn at_eof(&mut self) -> bool {
-        match self.inner.peek().unwrap() {
-            Token::Eof => true,
-            _ => false,
-        }
+        matches!(self.peek_kind(), TokenKind::Eof)
     }
 }
 
@@ -563,17 +2623,65 @@ pub enum Operator2 {
     Sub,
     Mul,
     Div,
+    Mod,
+    Shl,
+    Shr,
+    BitAnd,
+    BitOr,
+    BitXor,
     Eq,
     Ne,
     Lt,
     Lte,
 }
 
-#[derive(Debug, Clone)]
+impl Operator2 {
+    /// 比較演算子なら結果は 0 か 1 の int
+    pub fn is_comparison(&self) -> bool {
+        matches!(
+            self,
+            Operator2::Eq | Operator2::Ne | Operator2::Lt | Operator2::Lte
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Type {
+    Char,
+    UChar,
+    Short,
+    UShort,
     Int,
+    UInt,
+    Long,
+    ULong,
+    LongLong,
+    ULongLong,
     Ptr(Box<Self>),
     Array(Box<Self>, usize),
+    Struct(StructType),
+    Void,
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Char => write!(f, "char"),
+            Self::UChar => write!(f, "unsigned char"),
+            Self::Short => write!(f, "short"),
+            Self::UShort => write!(f, "unsigned short"),
+            Self::Int => write!(f, "int"),
+            Self::UInt => write!(f, "unsigned int"),
+            Self::Long => write!(f, "long"),
+            Self::ULong => write!(f, "unsigned long"),
+            Self::LongLong => write!(f, "long long"),
+            Self::ULongLong => write!(f, "unsigned long long"),
+            Self::Ptr(ty) => write!(f, "{}*", ty),
+            Self::Array(ty, num) => write!(f, "{}[{}]", ty, num),
+            Self::Struct(st) => write!(f, "{}", st),
+            Self::Void => write!(f, "void"),
+        }
+    }
 }
 
 impl Type {
@@ -583,18 +2691,168 @@ impl Type {
             _ => None,
         }
     }
+    /// 式として評価したときの型. 配列は先頭要素へのポインタになる
+    pub fn decay(self) -> Self {
+        match self {
+            Self::Array(ty, _) => Self::Ptr(ty),
+            _ => self,
+        }
+    }
+
     pub fn size(&self) -> usize {
         match self {
-            Self::Int => 4,
+            Self::Char | Self::UChar => 1,
+            Self::Short | Self::UShort => 2,
+            Self::Int | Self::UInt => 4,
+            Self::Long | Self::ULong | Self::LongLong | Self::ULongLong => 8,
             Self::Ptr(_) => 8,
-            Self::Array(ty, num) => ty.size() * num,
+            Self::Array(..) => self
+                .checked_size()
+                .expect("配列の大きさは宣言したときに確かめています"),
+            Self::Struct(st) => st.size(),
+            // void * の演算は 1 バイト単位で行う
+            Self::Void => 1,
+        }
+    }
+
+    /// 型の大きさ. 配列が大きすぎて usize に収まらなければ `None`
+    pub fn checked_size(&self) -> Option<usize> {
+        match self {
+            Self::Array(ty, num) => ty.checked_size()?.checked_mul(*num),
+            _ => Some(self.size()),
+        }
+    }
+
+    pub fn is_integer(&self) -> bool {
+        !matches!(
+            self,
+            Self::Ptr(_) | Self::Array(..) | Self::Struct(_) | Self::Void
+        )
+    }
+
+    /// 大きさの決まっていない型か. 中身が決まっていない構造体とその配列, それに void が当たる
+    pub fn is_incomplete(&self) -> bool {
+        match self {
+            Self::Array(ty, _) => ty.is_incomplete(),
+            Self::Struct(st) => !st.is_complete(),
+            Self::Void => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_unsigned(&self) -> bool {
+        matches!(
+            self,
+            Self::UChar | Self::UShort | Self::UInt | Self::ULong | Self::ULongLong
+        )
+    }
+
+    /// 整数変換の順位
+    fn rank(&self) -> usize {
+        match self {
+            Self::Char | Self::UChar => 1,
+            Self::Short | Self::UShort => 2,
+            Self::Int | Self::UInt => 3,
+            Self::Long | Self::ULong => 4,
+            Self::LongLong | Self::ULongLong => 5,
+            Self::Ptr(_) | Self::Array(..) | Self::Struct(_) | Self::Void => 0,
+        }
+    }
+
+    fn to_unsigned(&self) -> Self {
+        match self {
+            Self::Char => Self::UChar,
+            Self::Short => Self::UShort,
+            Self::Int => Self::UInt,
+            Self::Long => Self::ULong,
+            Self::LongLong => Self::ULongLong,
+            _ => self.clone(),
+        }
+    }
+
+    /// 汎整数拡張. int より小さい型は int になる
+    pub fn promoted(&self) -> Self {
+        if self.is_integer() && self.rank() < Self::Int.rank() {
+            Self::Int
+        } else {
+            self.clone()
+        }
+    }
+
+    /// 通常の算術型変換で決まる `left` と `right` の共通の型
+    pub fn common(left: &Self, right: &Self) -> Self {
+        let (left, right) = (left.promoted(), right.promoted());
+        if left == right {
+            return left;
+        }
+
+        let (signed, unsigned) = match (left.is_unsigned(), right.is_unsigned()) {
+            (false, false) | (true, true) => {
+                return if left.rank() >= right.rank() {
+                    left
+                } else {
+                    right
+                };
+            }
+            (false, true) => (left, right),
+            (true, false) => (right, left),
+        };
+
+        if unsigned.rank() >= signed.rank() {
+            unsigned
+        } else if signed.size() > unsigned.size() {
+            signed
+        } else {
+            signed.to_unsigned()
+        }
+    }
+
+    /// 整数定数を `self` 型に変換した値
+    pub fn convert_constant(&self, value: i64) -> i64 {
+        match self {
+            Self::Char => value as i8 as i64,
+            Self::UChar => value as u8 as i64,
+            Self::Short => value as i16 as i64,
+            Self::UShort => value as u16 as i64,
+            Self::Int => value as i32 as i64,
+            Self::UInt => value as u32 as i64,
+            _ => value,
+        }
+    }
+
+    /// 整数型で表せる最大値. 整数型でなければ 0
+    pub fn max_value(&self) -> u64 {
+        match self {
+            Self::Char => i8::MAX as u64,
+            Self::UChar => u8::MAX as u64,
+            Self::Short => i16::MAX as u64,
+            Self::UShort => u16::MAX as u64,
+            Self::Int => i32::MAX as u64,
+            Self::UInt => u32::MAX as u64,
+            Self::Long | Self::LongLong => i64::MAX as u64,
+            Self::ULong | Self::ULongLong => u64::MAX,
+            Self::Ptr(_) | Self::Array(..) | Self::Struct(_) | Self::Void => 0,
+        }
+    }
+
+    /// System V ABI で戻り値をメモリ経由で返す型か. 16 バイトを超える構造体が当たる
+    pub fn is_returned_in_memory(&self) -> bool {
+        matches!(self, Self::Struct(_)) && self.size() > 16
+    }
+
+    /// 変数を置くときのアラインメント
+    pub fn align(&self) -> usize {
+        match self {
+            Self::Array(ty, _) => ty.align(),
+            Self::Struct(st) => st.align(),
+            _ => self.size(),
         }
     }
 
     pub fn aligned_size(&self) -> usize {
         let size = self.size();
 
-        if size % REGISTER_SIZE == 0 {
+        if size.is_multiple_of(REGISTER_SIZE) {
             size
         } else {
             size + REGISTER_SIZE - (size % REGISTER_SIZE)
@@ -609,6 +2867,154 @@ impl Type {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StructKind {
+    Struct,
+    Union,
+}
+
+/// 構造体と共用体の型. 前方宣言の後で中身が決まるので, 同じ型は中身を共有する
+#[derive(Clone)]
+pub struct StructType(Rc<RefCell<StructBody>>);
+
+struct StructBody {
+    kind: StructKind,
+    tag: Option<String>,
+    /// 前方宣言だけでまだ中身が決まっていなければ None
+    members: Option<Vec<Member>>,
+    size: usize,
+    align: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Member {
+    /// 名前のない構造体や共用体のメンバは None
+    name: Option<String>,
+    ty: Type,
+    offset: usize,
+}
+
+impl Member {
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+    pub fn ty(&self) -> &Type {
+        &self.ty
+    }
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl StructType {
+    fn new(kind: StructKind, tag: Option<String>) -> Self {
+        Self(Rc::new(RefCell::new(StructBody {
+            kind,
+            tag,
+            members: None,
+            size: 0,
+            align: 1,
+        })))
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.0.borrow().members.is_some()
+    }
+
+    pub fn size(&self) -> usize {
+        self.0.borrow().size
+    }
+
+    pub fn align(&self) -> usize {
+        self.0.borrow().align
+    }
+
+    /// メンバを System V ABI と同じ規則で並べる
+    fn define(&self, members: Vec<(Option<String>, Type)>) {
+        let mut body = self.0.borrow_mut();
+        let mut offset: usize = 0;
+        let mut size: usize = 0;
+        let mut align = 1;
+        let mut laid_out = vec![];
+
+        for (name, ty) in members {
+            align = align.max(ty.align());
+            let member_offset = match body.kind {
+                StructKind::Struct => offset.next_multiple_of(ty.align()),
+                StructKind::Union => 0,
+            };
+            offset = member_offset + ty.size();
+            size = size.max(offset);
+            laid_out.push(Member {
+                name,
+                ty,
+                offset: member_offset,
+            });
+        }
+
+        body.members = Some(laid_out);
+        body.size = size.next_multiple_of(align);
+        body.align = align;
+    }
+
+    pub fn kind(&self) -> StructKind {
+        self.0.borrow().kind
+    }
+
+    /// 宣言した順のメンバ. 不完全型なら空
+    pub fn members(&self) -> Vec<Member> {
+        self.0.borrow().members.clone().unwrap_or_default()
+    }
+
+    /// 名前のないメンバの中まで探してメンバを返す. オフセットは `self` の先頭から
+    pub fn member(&self, name: &str) -> Option<Member> {
+        let body = self.0.borrow();
+        for member in body.members.as_ref()? {
+            match (&member.name, &member.ty) {
+                (Some(n), _) if n == name => return Some(member.clone()),
+                (None, Type::Struct(inner)) => {
+                    if let Some(found) = inner.member(name) {
+                        return Some(Member {
+                            offset: member.offset + found.offset,
+                            ..found
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
+/// 同じ宣言から作られた型だけが等しい
+impl PartialEq for StructType {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+// 自分自身へのポインタをメンバに持てるので, 中身までは辿らない
+impl std::fmt::Debug for StructType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+impl Display for StructType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let body = self.0.borrow();
+        let keyword = match body.kind {
+            StructKind::Struct => "struct",
+            StructKind::Union => "union",
+        };
+        match &body.tag {
+            Some(tag) => write!(f, "{} {}", keyword, tag),
+            None => write!(f, "{} <anonymous>", keyword),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct LocalVariable {
     name: String,
@@ -631,6 +3037,77 @@ impl LocalVariable {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct GlobalVariable {
+    name: String,
+    ty: Type,
+}
+
+impl GlobalVariable {
+    pub fn new(name: String, ty: Type) -> Self {
+        Self { name, ty }
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn ty(&self) -> &Type {
+        &self.ty
+    }
+}
+
+/// グローバル変数の初期値として `.data` に並べる値
+#[derive(Debug, Clone)]
+pub enum StaticValue {
+    /// 値と大きさ(バイト)
+    Int(i64, usize),
+    /// `target` のアドレスに `offset` バイト足したもの
+    Address(StaticTarget, i64),
+    /// 0 で埋めるバイト数
+    Zero(usize),
+}
+
+impl Display for StaticValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Int(value, _) => write!(f, "{}", value),
+            Self::Address(StaticTarget::Symbol(name), offset) => write!(f, "&{}{:+}", name, offset),
+            Self::Address(StaticTarget::StringLiteral(bytes), offset) => {
+                write!(f, "\"{}\"{:+}", bytes.escape_ascii(), offset)
+            }
+            Self::Zero(size) => write!(f, "zero({})", size),
+        }
+    }
+}
+
+/// アドレス定数が指す先
+#[derive(Debug, Clone)]
+pub enum StaticTarget {
+    Symbol(String),
+    StringLiteral(Vec<u8>),
+}
+
+#[derive(Debug, Clone)]
+pub struct DefineGlobalVariable {
+    name: String,
+    ty: Type,
+    init: Option<Vec<StaticValue>>,
+}
+
+impl DefineGlobalVariable {
+    pub fn new(name: String, ty: Type, init: Option<Vec<StaticValue>>) -> Self {
+        Self { name, ty, init }
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn ty(&self) -> &Type {
+        &self.ty
+    }
+    pub fn init(&self) -> Option<&[StaticValue]> {
+        self.init.as_deref()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct IfElse {
     condition: Box<Node>,
@@ -650,10 +3127,10 @@ impl IfElse {
             else_statement,
         }
     }
-    pub fn condition(&self) -> &Box<Node> {
+    pub fn condition(&self) -> &Node {
         &self.condition
     }
-    pub fn then_statement(&self) -> &Box<Node> {
+    pub fn then_statement(&self) -> &Node {
         &self.then_statement
     }
     pub fn else_statement(&self) -> &Option<Box<Node>> {
@@ -692,9 +3169,63 @@ impl For {
     pub fn next(&self) -> &Option<Box<Node>> {
         &self.next
     }
-    pub fn body(&self) -> &Box<Node> {
+    pub fn body(&self) -> &Node {
+        &self.body
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DoWhile {
+    body: Box<Node>,
+    cond: Box<Node>,
+}
+
+impl DoWhile {
+    pub fn new(body: Box<Node>, cond: Box<Node>) -> Self {
+        Self { body, cond }
+    }
+    pub fn body(&self) -> &Node {
         &self.body
     }
+    pub fn cond(&self) -> &Node {
+        &self.cond
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Switch {
+    cond: Box<Node>,
+    /// 汎整数拡張した制御式の型
+    ty: Type,
+    /// 本体を読み終えるまでは None
+    body: Option<Box<Node>>,
+    /// 出てきた順の case の値. 制御式の型に変換してある
+    cases: Vec<i64>,
+    has_default: bool,
+}
+
+impl Switch {
+    fn new(cond: Box<Node>, ty: Type) -> Self {
+        Self {
+            cond,
+            ty,
+            body: None,
+            cases: vec![],
+            has_default: false,
+        }
+    }
+    pub fn cond(&self) -> &Node {
+        &self.cond
+    }
+    pub fn body(&self) -> &Node {
+        self.body.as_ref().expect("switch 文の本体がありません")
+    }
+    pub fn cases(&self) -> &[i64] {
+        &self.cases
+    }
+    pub fn has_default(&self) -> bool {
+        self.has_default
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -702,6 +3233,10 @@ pub struct CallFunction {
     name: String,
     args: Vec<Node>,
     return_type: Option<Type>,
+    /// 構造体の戻り値を受け取る領域のオフセット
+    return_buffer: Option<usize>,
+    /// 可変長引数の関数の呼び出しか
+    variadic: bool,
 }
 
 impl CallFunction {
@@ -712,11 +3247,27 @@ impl CallFunction {
         &self.args
     }
 
-    pub fn new(name: String, args: Vec<Node>, return_type: Option<Type>) -> Self {
+    pub fn return_buffer(&self) -> Option<usize> {
+        self.return_buffer
+    }
+
+    pub fn variadic(&self) -> bool {
+        self.variadic
+    }
+
+    pub fn new(
+        name: String,
+        args: Vec<Node>,
+        return_type: Option<Type>,
+        return_buffer: Option<usize>,
+        variadic: bool,
+    ) -> Self {
         Self {
             name,
             args,
             return_type,
+            return_buffer,
+            variadic,
         }
     }
 }
@@ -724,27 +3275,44 @@ impl CallFunction {
 #[derive(Debug, Clone)]
 pub struct DefineFunction {
     name: String,
+    return_type: Type,
     params: Vec<Parameter>,
     statements: Vec<Node>,
+    /// 引数を含むローカル変数に必要なスタックの大きさ
+    stack_size: usize,
 }
 
 impl DefineFunction {
-    pub fn new(name: String, params: Vec<Parameter>, statements: Vec<Node>) -> Self {
+    pub fn new(
+        name: String,
+        return_type: Type,
+        params: Vec<Parameter>,
+        statements: Vec<Node>,
+        stack_size: usize,
+    ) -> Self {
         Self {
             name,
+            return_type,
             params,
             statements,
+            stack_size,
         }
     }
     pub fn name(&self) -> &str {
         &self.name
     }
+    pub fn return_type(&self) -> &Type {
+        &self.return_type
+    }
     pub fn params(&self) -> &Vec<Parameter> {
         &self.params
     }
     pub fn statements(&self) -> &Vec<Node> {
         &self.statements
     }
+    pub fn stack_size(&self) -> usize {
+        self.stack_size
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -775,73 +3343,298 @@ impl DefineVariable {
 }
 
 #[derive(Debug, Clone)]
-pub enum Node {
+pub enum NodeKind {
     Operator2 {
         op: Operator2,
-        left: Box<Self>,
-        right: Box<Self>,
+        left: Box<Node>,
+        right: Box<Node>,
     },
     Assign {
-        left: Box<Self>,
-        right: Box<Self>,
+        left: Box<Node>,
+        right: Box<Node>,
+    },
+    /// `left op= right`
+    CompoundAssign {
+        op: Operator2,
+        left: Box<Node>,
+        right: Box<Node>,
+    },
+    LogicalAnd {
+        left: Box<Node>,
+        right: Box<Node>,
     },
-    Addr(Box<Self>),
-    Deref(Box<Self>),
+    LogicalOr {
+        left: Box<Node>,
+        right: Box<Node>,
+    },
+    /// `cond ? then : else`
+    Conditional(IfElse),
+    Neg(Box<Node>),
+    Not(Box<Node>),
+    BitNot(Box<Node>),
+    Addr(Box<Node>),
+    Deref(Box<Node>),
     CallFunction(CallFunction),
     DefineFunction(DefineFunction),
     IfElse(IfElse),
     For(For),
-    Return(Box<Self>),
+    DoWhile(DoWhile),
+    Switch(Switch),
+    /// switch 文の中で何番目の case か
+    Case(usize, Box<Node>),
+    Default(Box<Node>),
+    Break,
+    Continue,
+    Goto(String),
+    Label(String, Box<Node>),
+    /// `return;` なら None
+    Return(Option<Box<Node>>),
     LocalVariable(LocalVariable),
-    Num(i64),
+    GlobalVariable(GlobalVariable),
+    /// 式の値を指定した型に変換する
+    Cast(Box<Node>, Type),
+    /// 構造体や共用体のメンバ
+    Member(Box<Node>, Member),
+    DefineGlobalVariable(DefineGlobalVariable),
+    Num(i64, Type),
+    /// 終端の 0 を含まないバイト列
+    StringLiteral(Vec<u8>),
     Block(Vec<Node>),
     DefineVariable(DefineVariable),
     RelativeAddress(usize, Type),
 }
 
+#[derive(Debug, Clone)]
+pub struct Node {
+    kind: NodeKind,
+    span: Span,
+}
+
 impl Node {
-    pub fn new_op2(op: Operator2, left: Box<Self>, right: Box<Self>) -> Self {
-        Self::Operator2 { op, left, right }
+    pub fn new(kind: NodeKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+
+    pub fn new_op2(op: Operator2, left: Box<Self>, right: Box<Self>, span: Span) -> Self {
+        Self::new(NodeKind::Operator2 { op, left, right }, span)
+    }
+
+    pub fn new_assign(left: Box<Self>, right: Box<Self>, span: Span) -> Self {
+        Self::new(NodeKind::Assign { left, right }, span)
+    }
+
+    pub fn num(n: i64, span: Span) -> Self {
+        Self::new(NodeKind::Num(n, Type::Int), span)
     }
 
-    pub fn new_assign(left: Box<Self>, right: Box<Self>) -> Self {
-        Self::Assign { left, right }
+    pub fn kind(&self) -> &NodeKind {
+        &self.kind
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
     }
 
     pub fn as_local_value(&self) -> Option<&LocalVariable> {
-        match self {
-            Self::LocalVariable(s) => Some(s),
+        match &self.kind {
+            NodeKind::LocalVariable(s) => Some(s),
             _ => None,
         }
     }
 
     pub fn declare_type(&self) -> Option<Type> {
-        match self {
-            Self::LocalVariable(lv) => Some(lv.ty.clone()),
-            Self::Operator2 { left, .. } => Some(left.declare_type()?),
-            Self::Deref(v) => Some(
-                v.declare_type()?
-                    .as_ptr()
-                    .cloned()
-                    .expect("Derefの中身はポインタ型に暗黙的に変換出来る型")
-                    .clone(),
-            ),
-            Self::Addr(v) => Some(Type::Ptr(Box::new(v.declare_type().unwrap().clone()))),
-            Self::Num(_) => Some(Type::Int),
-            Self::CallFunction(cf) => cf.return_type.clone(),
-            Self::RelativeAddress(_, ty) => Some(ty.clone()),
+        match &self.kind {
+            NodeKind::LocalVariable(lv) => Some(lv.ty.clone()),
+            NodeKind::GlobalVariable(gv) => Some(gv.ty.clone()),
+            NodeKind::Member(_, member) => Some(member.ty.clone()),
+            NodeKind::Operator2 {
+                op: Operator2::Sub,
+                left,
+                right,
+            } if left.pointee_size().is_some() && right.pointee_size().is_some() => {
+                Some(Type::Long)
+            }
+            NodeKind::Operator2 { op, .. } if op.is_comparison() => Some(Type::Int),
+            NodeKind::Operator2 { left, .. } => Some(left.declare_type()?.decay()),
+            NodeKind::Assign { left, .. } | NodeKind::CompoundAssign { left, .. } => {
+                left.declare_type()
+            }
+            NodeKind::LogicalAnd { .. } | NodeKind::LogicalOr { .. } | NodeKind::Not(_) => {
+                Some(Type::Int)
+            }
+            NodeKind::Conditional(c) => c.then_statement().declare_type(),
+            NodeKind::Neg(v) | NodeKind::BitNot(v) => v.declare_type(),
+            NodeKind::Deref(v) => v.declare_type()?.decay().as_ptr().cloned(),
+            NodeKind::Addr(v) => Some(Type::Ptr(Box::new(v.declare_type()?))),
+            NodeKind::Num(_, ty) => Some(ty.clone()),
+            NodeKind::StringLiteral(bytes) => {
+                Some(Type::Array(Box::new(Type::Char), bytes.len() + 1))
+            }
+            NodeKind::CallFunction(cf) => cf.return_type.clone(),
+            NodeKind::RelativeAddress(_, ty) | NodeKind::Cast(_, ty) => Some(ty.clone()),
+            _ => None,
+        }
+    }
+
+    /// ポインタ(に変換される配列)なら指す先の大きさ
+    fn pointee_size(&self) -> Option<usize> {
+        match self.declare_type()? {
+            Type::Ptr(ty) | Type::Array(ty, _) => Some(ty.size()),
+            _ => None,
+        }
+    }
+
+    /// ポインタ演算のために `size` 倍する
+    fn scale(self, size: usize, span: Span) -> Self {
+        let offset = self.cast(Type::Long);
+        if size == 1 {
+            offset
+        } else {
+            Self::new_op2(
+                Operator2::Mul,
+                Box::new(offset),
+                Box::new(Self::num(size as i64, span).cast(Type::Long)),
+                span,
+            )
+        }
+    }
+
+    /// 型が `ty` と異なれば変換を挟む
+    fn cast(self, ty: Type) -> Self {
+        if self.declare_type().as_ref() == Some(&ty) {
+            return self;
+        }
+        let span = self.span;
+        Self::new(NodeKind::Cast(Box::new(self), ty), span)
+    }
+
+    /// 代入と同じ規則で `ty` 型に変換する. 整数は変換を挟み, ポインタと構造体はそのまま使う
+    fn convert_as_if_by_assignment(self, ty: &Type) -> ParseResult<Conversion> {
+        let actual = self
+            .declare_type()
+            .ok_or(ParseError::UndeterminedType(self.span))?
+            .decay();
+        let conversion = match (&actual, ty) {
+            (a, t) if a.is_integer() && t.is_integer() => {
+                Conversion::Converted(self.cast(ty.clone()))
+            }
+            // void * はどのポインタとも変換できる
+            (Type::Ptr(a), Type::Ptr(t)) if a != t && **a != Type::Void && **t != Type::Void => {
+                Conversion::IncompatiblePointer(self, actual)
+            }
+            (Type::Ptr(_), Type::Ptr(_)) => Conversion::Converted(self),
+            // 整数の 0 はヌルポインタ定数
+            (a, Type::Ptr(_)) if a.is_integer() && self.eval_constant() == Some(0) => {
+                Conversion::Converted(self)
+            }
+            (a, t) if a == t => Conversion::Converted(self),
+            _ => Conversion::Invalid(actual),
+        };
+        Ok(conversion)
+    }
+
+    /// 汎整数拡張を挟む
+    fn promote(self) -> Self {
+        match self.declare_type() {
+            Some(ty) if ty.is_integer() => self.cast(ty.promoted()),
+            _ => self,
+        }
+    }
+
+    /// 整数定数式なら値を計算する
+    pub fn eval_constant(&self) -> Option<i64> {
+        let value = match &self.kind {
+            NodeKind::Num(n, _) => *n,
+            NodeKind::Cast(v, ty) => ty.convert_constant(v.eval_constant()?),
+            NodeKind::Operator2 { op, left, right } => {
+                let (l, r) = (left.eval_constant()?, right.eval_constant()?);
+                let unsigned = left.declare_type()?.is_unsigned();
+                let value = match op {
+                    Operator2::Div if unsigned => (l as u64).checked_div(r as u64)? as i64,
+                    Operator2::Mod if unsigned => (l as u64).checked_rem(r as u64)? as i64,
+                    Operator2::Shr if unsigned => (l as u64).wrapping_shr(r as u32) as i64,
+                    Operator2::Lt if unsigned => ((l as u64) < (r as u64)) as i64,
+                    Operator2::Lte if unsigned => ((l as u64) <= (r as u64)) as i64,
+                    Operator2::Add => l.wrapping_add(r),
+                    Operator2::Sub => l.wrapping_sub(r),
+                    Operator2::Mul => l.wrapping_mul(r),
+                    Operator2::Div => l.checked_div(r)?,
+                    Operator2::Mod => l.checked_rem(r)?,
+                    Operator2::Shl => l.wrapping_shl(r as u32),
+                    Operator2::Shr => l.wrapping_shr(r as u32),
+                    Operator2::BitAnd => l & r,
+                    Operator2::BitOr => l | r,
+                    Operator2::BitXor => l ^ r,
+                    Operator2::Eq => (l == r) as i64,
+                    Operator2::Ne => (l != r) as i64,
+                    Operator2::Lt => (l < r) as i64,
+                    Operator2::Lte => (l <= r) as i64,
+                };
+                self.declare_type()?.convert_constant(value)
+            }
+            NodeKind::LogicalAnd { left, right } => {
+                (left.eval_constant()? != 0 && right.eval_constant()? != 0) as i64
+            }
+            NodeKind::LogicalOr { left, right } => {
+                (left.eval_constant()? != 0 || right.eval_constant()? != 0) as i64
+            }
+            NodeKind::Conditional(c) => {
+                if c.condition().eval_constant()? != 0 {
+                    c.then_statement().eval_constant()?
+                } else {
+                    c.else_statement().as_ref()?.eval_constant()?
+                }
+            }
+            NodeKind::Neg(v) => v
+                .declare_type()?
+                .convert_constant(v.eval_constant()?.wrapping_neg()),
+            NodeKind::Not(v) => (v.eval_constant()? == 0) as i64,
+            NodeKind::BitNot(v) => v.declare_type()?.convert_constant(!v.eval_constant()?),
+            _ => return None,
+        };
+        Some(value)
+    }
+
+    /// `&x` や `a + 1`, 文字列リテラルのようなアドレス定数なら指す先とオフセットを返す
+    pub fn eval_address(&self) -> Option<(StaticTarget, i64)> {
+        match &self.kind {
+            NodeKind::Addr(v) => match &v.kind {
+                NodeKind::GlobalVariable(gv) => Some((StaticTarget::Symbol(gv.name.clone()), 0)),
+                NodeKind::Deref(v) => v.eval_address(),
+                _ => None,
+            },
+            NodeKind::GlobalVariable(GlobalVariable {
+                name,
+                ty: Type::Array(..),
+            }) => Some((StaticTarget::Symbol(name.clone()), 0)),
+            NodeKind::StringLiteral(bytes) => Some((StaticTarget::StringLiteral(bytes.clone()), 0)),
+            NodeKind::Operator2 {
+                op: op @ (Operator2::Add | Operator2::Sub),
+                left,
+                right,
+            } => {
+                let (target, offset) = left.eval_address()?;
+                let delta = right.eval_constant()?;
+                match op {
+                    Operator2::Add => Some((target, offset + delta)),
+                    _ => Some((target, offset - delta)),
+                }
+            }
             _ => None,
         }
     }
 
     fn array_access_to_relative_address(self) -> Self {
-        match self {
-            Self::LocalVariable(LocalVariable {
+        match self.kind {
+            NodeKind::LocalVariable(LocalVariable {
                 ty: Type::Array(inner_ty, _),
                 offset,
                 ..
-            }) => Self::RelativeAddress(offset, Type::Ptr(Box::new(*inner_ty.clone()))),
-            x => x,
+            }) => Self::new(
+                NodeKind::RelativeAddress(offset, Type::Ptr(Box::new(*inner_ty.clone()))),
+                self.span,
+            ),
+            kind => Self::new(kind, self.span),
         }
     }
 }
@@ -0,0 +1,59 @@
+/// 入力されたソースファイル. `id` は `Span` から参照される
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    id: usize,
+    name: String,
+    contents: String,
+}
+
+impl SourceFile {
+    pub fn new(id: usize, name: String, contents: String) -> Self {
+        Self { id, name, contents }
+    }
+    pub fn id(&self) -> usize {
+        self.id
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn contents(&self) -> &str {
+        &self.contents
+    }
+
+    /// 1始まりの行番号で行を取り出す
+    pub fn line(&self, line: usize) -> &str {
+        self.contents.lines().nth(line - 1).unwrap_or("")
+    }
+}
+
+/// ソースコード上の位置. `offset` はバイト位置, `line` と `column` は1始まり
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Span {
+    file_id: usize,
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Span {
+    pub fn new(file_id: usize, offset: usize, line: usize, column: usize) -> Self {
+        Self {
+            file_id,
+            offset,
+            line,
+            column,
+        }
+    }
+    pub fn file_id(&self) -> usize {
+        self.file_id
+    }
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+    pub fn line(&self) -> usize {
+        self.line
+    }
+    pub fn column(&self) -> usize {
+        self.column
+    }
+}
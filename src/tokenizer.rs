@@ -1,50 +1,159 @@
+use crate::parser::Type;
+use crate::source::{SourceFile, Span};
 use std::error::Error;
 use std::fmt::{Display, Formatter};
 use std::iter::Peekable;
-use std::str::FromStr;
 
 #[derive(Debug, Clone)]
-pub enum Token {
+pub enum TokenKind {
     Reserved(String),
     Ident(String),
-    Num(i64),
+    /// 整数定数. 型は接尾辞と値から C の規則で決める
+    Num(i64, Type),
+    Str(Vec<u8>),
     Return,
     If,
     Else,
     For,
     While,
+    Do,
+    Break,
+    Continue,
+    Goto,
+    Switch,
+    Case,
+    Default,
+    Void,
+    Char,
+    Short,
     Int,
+    Long,
+    Signed,
+    Unsigned,
+    Struct,
+    Union,
+    Enum,
+    Typedef,
     Sizeof,
     Eof,
 }
 
-impl Token {
+impl TokenKind {
     pub fn reserved(s: &str) -> Self {
-        Token::Reserved(s.to_string())
+        TokenKind::Reserved(s.to_string())
     }
 
     pub fn as_reserved(&self) -> Option<&String> {
         match self {
-            Token::Reserved(s) => Some(s),
+            TokenKind::Reserved(s) => Some(s),
             _ => None,
         }
     }
 
     pub fn as_num(&self) -> Option<i64> {
         match self {
-            Token::Num(n) => Some(*n),
+            TokenKind::Num(n, _) => Some(*n),
             _ => None,
         }
     }
 
     pub fn as_eof(&self) -> bool {
+        matches!(self, TokenKind::Eof)
+    }
+
+    /// 識別子として切り出した語がキーワードならそのトークンを返す
+    pub fn keyword(word: &str) -> Option<Self> {
+        match word {
+            "return" => Some(TokenKind::Return),
+            "if" => Some(TokenKind::If),
+            "else" => Some(TokenKind::Else),
+            "for" => Some(TokenKind::For),
+            "while" => Some(TokenKind::While),
+            "do" => Some(TokenKind::Do),
+            "break" => Some(TokenKind::Break),
+            "continue" => Some(TokenKind::Continue),
+            "goto" => Some(TokenKind::Goto),
+            "switch" => Some(TokenKind::Switch),
+            "case" => Some(TokenKind::Case),
+            "default" => Some(TokenKind::Default),
+            "void" => Some(TokenKind::Void),
+            "char" => Some(TokenKind::Char),
+            "short" => Some(TokenKind::Short),
+            "int" => Some(TokenKind::Int),
+            "long" => Some(TokenKind::Long),
+            "signed" => Some(TokenKind::Signed),
+            "unsigned" => Some(TokenKind::Unsigned),
+            "struct" => Some(TokenKind::Struct),
+            "union" => Some(TokenKind::Union),
+            "enum" => Some(TokenKind::Enum),
+            "typedef" => Some(TokenKind::Typedef),
+            "sizeof" => Some(TokenKind::Sizeof),
+            _ => None,
+        }
+    }
+}
+
+impl Display for TokenKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
-            Token::Eof => true,
-            _ => false,
+            TokenKind::Reserved(s) => write!(f, "{}", s),
+            TokenKind::Ident(name) => write!(f, "{}", name),
+            TokenKind::Num(n, ty) if ty.is_unsigned() => write!(f, "{}", *n as u64),
+            TokenKind::Num(n, _) => write!(f, "{}", n),
+            TokenKind::Str(bytes) => write!(f, "\"{}\"", bytes.escape_ascii()),
+            TokenKind::Return => write!(f, "return"),
+            TokenKind::If => write!(f, "if"),
+            TokenKind::Else => write!(f, "else"),
+            TokenKind::For => write!(f, "for"),
+            TokenKind::While => write!(f, "while"),
+            TokenKind::Do => write!(f, "do"),
+            TokenKind::Break => write!(f, "break"),
+            TokenKind::Continue => write!(f, "continue"),
+            TokenKind::Goto => write!(f, "goto"),
+            TokenKind::Switch => write!(f, "switch"),
+            TokenKind::Case => write!(f, "case"),
+            TokenKind::Default => write!(f, "default"),
+            TokenKind::Void => write!(f, "void"),
+            TokenKind::Char => write!(f, "char"),
+            TokenKind::Short => write!(f, "short"),
+            TokenKind::Int => write!(f, "int"),
+            TokenKind::Long => write!(f, "long"),
+            TokenKind::Signed => write!(f, "signed"),
+            TokenKind::Unsigned => write!(f, "unsigned"),
+            TokenKind::Struct => write!(f, "struct"),
+            TokenKind::Union => write!(f, "union"),
+            TokenKind::Enum => write!(f, "enum"),
+            TokenKind::Typedef => write!(f, "typedef"),
+            TokenKind::Sizeof => write!(f, "sizeof"),
+            TokenKind::Eof => write!(f, "EOF"),
         }
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct Token {
+    kind: TokenKind,
+    span: Span,
+}
+
+impl Token {
+    pub fn new(kind: TokenKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+    pub fn kind(&self) -> &TokenKind {
+        &self.kind
+    }
+    pub fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind)
+    }
+}
+
 #[derive(Debug)]
 pub struct GeneralError {
     message: String,
@@ -57,8 +166,8 @@ impl GeneralError {
 }
 
 impl Display for GeneralError {
-    fn fmt(&self, _f: &mut Formatter<'_>) -> std::fmt::Result {
-        todo!()
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
     }
 }
 
@@ -67,25 +176,31 @@ impl Error for GeneralError {}
 #[derive(Debug)]
 pub struct TokenizeError {
     message: String,
-    line_number: usize,
-    source_code_line: String,
-    pos: usize,
+    span: Span,
 }
 
 impl TokenizeError {
-    pub fn new(message: String, line_number: usize, source_code_line: String, pos: usize) -> Self {
-        Self {
-            message,
-            line_number,
-            source_code_line,
-            pos,
-        }
+    pub fn new(message: String, span: Span) -> Self {
+        Self { message, span }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+    pub fn span(&self) -> Span {
+        self.span
     }
 }
 
 impl Display for TokenizeError {
-    fn fmt(&self, _f: &mut Formatter<'_>) -> std::fmt::Result {
-        todo!()
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}",
+            self.span.line(),
+            self.span.column(),
+            self.message
+        )
     }
 }
 
@@ -93,23 +208,29 @@ impl Error for TokenizeError {}
 
 pub type TokenizeResult<T> = std::result::Result<T, TokenizeError>;
 
+/// 記号のトークン. 長いものから順に試す
+const PUNCTUATORS: [&str; 46] = [
+    "...", "<<=", ">>=", "==", "!=", "<=", ">=", "->", "++", "--", "&&", "||", "<<", ">>", "+=",
+    "-=", "*=", "/=", "%=", "&=", "|=", "^=", "+", "-", "*", "/", "%", "<", ">", "(", ")", ";",
+    "=", "{", "}", ",", "&", "[", "]", "|", "^", "!", "~", "?", ":", ".",
+];
+
 fn match_string<T: Iterator<Item = (usize, char)> + Clone>(p_iter: &T, s: &str) -> bool {
     let p_iter = p_iter.clone();
-    s.chars()
-        .zip(p_iter.take(s.len()))
-        .all(|(c1, (_, c2))| c1 == c2)
+    let mut chars = p_iter.map(|(_, c)| c);
+    s.chars().all(|c| chars.next() == Some(c))
 }
 
 fn match_variable_string<T: Iterator<Item = (usize, char)> + Clone>(p_iter: &T) -> Option<String> {
     let mut p_iter2 = p_iter.clone();
 
     if let Some((_, c)) = p_iter2.next() {
-        if c.is_ascii_alphabetic() {
+        if c.is_ascii_alphabetic() || c == '_' {
             let mut s = c.to_string();
             s.extend(
                 p_iter2
                     .map(|(_, c)| c)
-                    .take_while(|c| c.is_ascii_alphanumeric()),
+                    .take_while(|c| c.is_ascii_alphanumeric() || *c == '_'),
             );
             Some(s)
         } else {
@@ -120,150 +241,297 @@ fn match_variable_string<T: Iterator<Item = (usize, char)> + Clone>(p_iter: &T)
     }
 }
 
-pub fn tokenize(input: &str) -> TokenizeResult<Vec<Token>> {
-    let mut cs = input.chars().enumerate().peekable();
+pub fn tokenize(file: &SourceFile) -> TokenizeResult<Vec<Token>> {
+    let input = file.contents();
+    let mut cs = input.char_indices().peekable();
     let mut tokens = vec![];
+    let mut line = 1;
+    let mut line_start = 0;
 
     while let Some((pos, c)) = cs.peek().cloned() {
-        if match_string(&cs, "==") {
-            tokens.push(Token::Reserved("==".to_string()));
-            cs.next();
-            cs.next();
-        } else if match_string(&cs, "!=") {
-            tokens.push(Token::Reserved("!=".to_string()));
-            cs.next();
-            cs.next();
-        } else if match_string(&cs, ">=") {
-            tokens.push(Token::Reserved(">=".to_string()));
-            cs.next();
-            cs.next();
-        } else if match_string(&cs, "<=") {
-            tokens.push(Token::Reserved("<=".to_string()));
-            cs.next();
-            cs.next();
-        } else if match_string(&cs, "+") {
-            tokens.push(Token::Reserved("+".to_string()));
-            cs.next();
-        } else if match_string(&cs, "-") {
-            tokens.push(Token::Reserved("-".to_string()));
-            cs.next();
-        } else if match_string(&cs, "*") {
-            tokens.push(Token::Reserved("*".to_string()));
-            cs.next();
-        } else if match_string(&cs, "/") {
-            tokens.push(Token::Reserved("/".to_string()));
-            cs.next();
-        } else if match_string(&cs, "<") {
-            tokens.push(Token::Reserved("<".to_string()));
-            cs.next();
-        } else if match_string(&cs, ">") {
-            tokens.push(Token::Reserved(">".to_string()));
-            cs.next();
-        } else if match_string(&cs, "(") {
-            tokens.push(Token::Reserved("(".to_string()));
-            cs.next();
-        } else if match_string(&cs, ")") {
-            tokens.push(Token::Reserved(")".to_string()));
-            cs.next();
-        } else if match_string(&cs, ";") {
-            tokens.push(Token::Reserved(";".to_string()));
-            cs.next();
-        } else if match_string(&cs, "=") {
-            tokens.push(Token::Reserved("=".to_string()));
-            cs.next();
-        } else if match_string(&cs, "{") {
-            tokens.push(Token::Reserved("{".to_string()));
-            cs.next();
-        } else if match_string(&cs, "}") {
-            tokens.push(Token::Reserved("}".to_string()));
-            cs.next();
-        } else if match_string(&cs, ",") {
-            tokens.push(Token::Reserved(",".to_string()));
-            cs.next();
-        } else if match_string(&cs, "&") {
-            tokens.push(Token::Reserved("&".to_string()));
-            cs.next();
-        } else if match_string(&cs, "[") {
-            tokens.push(Token::Reserved("[".to_string()));
+        let span = Span::new(
+            file.id(),
+            pos,
+            line,
+            input[line_start..pos].chars().count() + 1,
+        );
+
+        if match_string(&cs, "//") {
+            while cs.next_if(|&(_, c)| c != '\n').is_some() {}
+        } else if match_string(&cs, "/*") {
             cs.next();
-        } else if match_string(&cs, "]") {
-            tokens.push(Token::Reserved("]".to_string()));
             cs.next();
-        } else if match_string(&cs, "return") {
-            tokens.push(Token::Return);
-            for _ in 0..6 {
-                cs.next();
-            }
-        } else if match_string(&cs, "if") {
-            tokens.push(Token::If);
-            for _ in 0..2 {
-                cs.next();
-            }
-        } else if match_string(&cs, "else") {
-            tokens.push(Token::Else);
-            for _ in 0..4 {
-                cs.next();
-            }
-        } else if match_string(&cs, "for") {
-            tokens.push(Token::For);
-            for _ in 0..3 {
-                cs.next();
-            }
-        } else if match_string(&cs, "while") {
-            tokens.push(Token::While);
-            for _ in 0..5 {
-                cs.next();
-            }
-        } else if match_string(&cs, "int") {
-            tokens.push(Token::Int);
-            for _ in 0..3 {
-                cs.next();
+            loop {
+                match cs.next() {
+                    Some((_, '*')) if cs.next_if(|&(_, c)| c == '/').is_some() => break,
+                    Some((p, '\n')) => {
+                        line += 1;
+                        line_start = p + 1;
+                    }
+                    Some(_) => {}
+                    None => {
+                        return Err(TokenizeError::new(
+                            "コメントが閉じられていません".to_string(),
+                            span,
+                        ))
+                    }
+                }
             }
-        } else if match_string(&cs, "sizeof") {
-            tokens.push(Token::Sizeof);
-            for _ in 0..6 {
+        } else if let Some(punctuator) = PUNCTUATORS.iter().find(|p| match_string(&cs, p)) {
+            tokens.push(Token::new(TokenKind::reserved(punctuator), span));
+            for _ in 0..punctuator.len() {
                 cs.next();
             }
-        } else if let Some(name) = match_variable_string(&mut cs) {
-            let n = name.len();
-            tokens.push(Token::Ident(name));
+        } else if let Some(word) = match_variable_string(&cs) {
+            let n = word.len();
+            let kind = TokenKind::keyword(&word).unwrap_or(TokenKind::Ident(word));
+            tokens.push(Token::new(kind, span));
             for _ in 0..n {
                 cs.next();
             }
         } else {
             match c {
-                ' ' | '\n' => {
+                ' ' | '\t' | '\r' | '\x0b' | '\x0c' => {
                     cs.next();
                 }
+                '\n' => {
+                    cs.next();
+                    line += 1;
+                    line_start = pos + 1;
+                }
                 c if c.is_ascii_digit() => {
-                    let num = parse_number(&mut cs)
-                        .map_err(|e| TokenizeError::new(e.message, 0, input.to_string(), pos))?;
-                    tokens.push(Token::Num(num));
+                    let (num, ty) =
+                        parse_number(&mut cs).map_err(|e| TokenizeError::new(e.message, span))?;
+                    tokens.push(Token::new(TokenKind::Num(num, ty), span));
+                }
+                '\'' => {
+                    cs.next();
+                    let c = parse_char_literal(&mut cs)
+                        .map_err(|e| TokenizeError::new(e.message, span))?;
+                    // char は符号付きなので '\xff' は -1
+                    tokens.push(Token::new(TokenKind::Num(c as i8 as i64, Type::Int), span));
+                }
+                '"' => {
+                    cs.next();
+                    let bytes = parse_string_literal(&mut cs)
+                        .map_err(|e| TokenizeError::new(e.message, span))?;
+                    tokens.push(Token::new(TokenKind::Str(bytes), span));
                 }
                 _ => {
                     return Err(TokenizeError::new(
                         "トークナイズ出来ません".to_string(),
-                        0,
-                        input.to_string(),
-                        pos,
+                        span,
                     ))
                 }
             }
         }
     }
 
-    tokens.push(Token::Eof);
+    let span = Span::new(
+        file.id(),
+        input.len(),
+        line,
+        input[line_start..].chars().count() + 1,
+    );
+    tokens.push(Token::new(TokenKind::Eof, span));
 
     Ok(tokens)
 }
 
+/// 整数定数を読み, 値と型を返す
+///
+/// `0x` / `0X` で16進数, `0b` / `0B` で2進数, `0` で始まれば8進数として読む.
+/// 型は C の規則どおり, 接尾辞が許す型のうち値が収まる最初のものにする.
 pub fn parse_number(
     iter: &mut Peekable<impl Iterator<Item = (usize, char)>>,
-) -> Result<i64, GeneralError> {
+) -> Result<(i64, Type), GeneralError> {
+    // 接尾辞まで含めて英数字の並びを1つの定数として切り出す
     let mut s = String::new();
-
-    while let Some((_pos, c)) = iter.next_if(|(_pos, c)| c.is_ascii_digit()) {
+    while let Some((_pos, c)) = iter.next_if(|(_pos, c)| c.is_ascii_alphanumeric()) {
         s.push(c);
     }
-    i64::from_str(s.as_str()).map_err(|_| GeneralError::new("整数がパースできません".to_string()))
+
+    let (radix, body) = if let Some(body) = s.strip_prefix("0x").or(s.strip_prefix("0X")) {
+        (16, body)
+    } else if let Some(body) = s.strip_prefix("0b").or(s.strip_prefix("0B")) {
+        (2, body)
+    } else if s.starts_with('0') {
+        (8, &s[..])
+    } else {
+        (10, &s[..])
+    };
+
+    let digits_end = body
+        .find(|c: char| !(c.is_ascii_digit() || radix == 16 && c.is_ascii_hexdigit()))
+        .unwrap_or(body.len());
+    let (digits, suffix) = body.split_at(digits_end);
+
+    if digits.is_empty() {
+        return Err(GeneralError::new(format!(
+            "整数定数 '{}' に数字がありません",
+            s
+        )));
+    }
+    if let Some(c) = digits.chars().find(|c| !c.is_digit(radix)) {
+        return Err(GeneralError::new(format!(
+            "{}進数の整数定数に '{}' は使えません",
+            radix, c
+        )));
+    }
+
+    let (unsigned, long) = match suffix {
+        "" => (false, 0),
+        "u" | "U" => (true, 0),
+        "l" | "L" => (false, 1),
+        "ul" | "uL" | "Ul" | "UL" | "lu" | "lU" | "Lu" | "LU" => (true, 1),
+        "ll" | "LL" => (false, 2),
+        "ull" | "uLL" | "Ull" | "ULL" | "llu" | "llU" | "LLu" | "LLU" => (true, 2),
+        _ => {
+            return Err(GeneralError::new(format!(
+                "整数定数の接尾辞 '{}' は不正です",
+                suffix
+            )))
+        }
+    };
+
+    let value = u64::from_str_radix(digits, radix)
+        .map_err(|_| GeneralError::new(format!("整数定数 '{}' が大きすぎます", s)))?;
+
+    // 10進数に u が付いていなければ符号付きの型だけが候補になる
+    let candidates: &[Type] = match (unsigned, long, radix == 10) {
+        (false, 0, true) => &[Type::Int, Type::Long, Type::LongLong],
+        (false, 0, false) => &[
+            Type::Int,
+            Type::UInt,
+            Type::Long,
+            Type::ULong,
+            Type::LongLong,
+            Type::ULongLong,
+        ],
+        (true, 0, _) => &[Type::UInt, Type::ULong, Type::ULongLong],
+        (false, 1, true) => &[Type::Long, Type::LongLong],
+        (false, 1, false) => &[Type::Long, Type::ULong, Type::LongLong, Type::ULongLong],
+        (true, 1, _) => &[Type::ULong, Type::ULongLong],
+        (false, _, true) => &[Type::LongLong],
+        (false, _, false) => &[Type::LongLong, Type::ULongLong],
+        (true, _, _) => &[Type::ULongLong],
+    };
+
+    candidates
+        .iter()
+        .find(|ty| value <= ty.max_value())
+        .map(|ty| (value as i64, ty.clone()))
+        .ok_or_else(|| GeneralError::new(format!("整数定数 '{}' が大きすぎます", s)))
+}
+
+/// `\` の直後から, エスケープシーケンス1つ分を読んでその値を返す
+fn parse_escape(
+    iter: &mut Peekable<impl Iterator<Item = (usize, char)>>,
+) -> Result<u8, GeneralError> {
+    let (_, c) = iter.next().ok_or_else(|| {
+        GeneralError::new("エスケープシーケンスが途中で終わっています".to_string())
+    })?;
+
+    let value = match c {
+        'n' => b'\n',
+        't' => b'\t',
+        'r' => b'\r',
+        'a' => 0x07,
+        'b' => 0x08,
+        'f' => 0x0c,
+        'v' => 0x0b,
+        'e' => 0x1b,
+        '\\' | '\'' | '"' | '?' => c as u8,
+        'x' => {
+            let mut value: u32 = 0;
+            let mut digits = 0;
+            while let Some((_, d)) = iter.next_if(|(_, c)| c.is_ascii_hexdigit()) {
+                value = value * 16 + d.to_digit(16).unwrap();
+                digits += 1;
+                if value > 0xff {
+                    return Err(GeneralError::new(
+                        "16進エスケープシーケンスが大きすぎます".to_string(),
+                    ));
+                }
+            }
+            if digits == 0 {
+                return Err(GeneralError::new(
+                    "\\x の後に16進数がありません".to_string(),
+                ));
+            }
+            value as u8
+        }
+        '0'..='7' => {
+            let mut value = c.to_digit(8).unwrap();
+            for _ in 0..2 {
+                match iter.next_if(|(_, c)| ('0'..='7').contains(c)) {
+                    Some((_, d)) => value = value * 8 + d.to_digit(8).unwrap(),
+                    None => break,
+                }
+            }
+            if value > 0xff {
+                return Err(GeneralError::new(
+                    "8進エスケープシーケンスが大きすぎます".to_string(),
+                ));
+            }
+            value as u8
+        }
+        c => {
+            return Err(GeneralError::new(format!(
+                "不明なエスケープシーケンスです: \\{}",
+                c
+            )))
+        }
+    };
+
+    Ok(value)
+}
+
+/// 開きの `'` の後から閉じの `'` までを読む
+fn parse_char_literal(
+    iter: &mut Peekable<impl Iterator<Item = (usize, char)>>,
+) -> Result<u8, GeneralError> {
+    let value = match iter.next() {
+        Some((_, '\\')) => parse_escape(iter)?,
+        Some((_, '\'')) => return Err(GeneralError::new("空の文字定数です".to_string())),
+        Some((_, c)) if c.is_ascii() && c != '\n' => c as u8,
+        Some((_, '\n')) | None => {
+            return Err(GeneralError::new(
+                "文字定数が閉じられていません".to_string(),
+            ))
+        }
+        Some(_) => {
+            return Err(GeneralError::new(
+                "ASCII 以外の文字定数には対応していません".to_string(),
+            ))
+        }
+    };
+
+    match iter.next() {
+        Some((_, '\'')) => Ok(value),
+        _ => Err(GeneralError::new(
+            "文字定数が閉じられていません".to_string(),
+        )),
+    }
+}
+
+/// 開きの `"` の後から閉じの `"` までを読んでバイト列にする. 終端の 0 は含まない
+fn parse_string_literal(
+    iter: &mut Peekable<impl Iterator<Item = (usize, char)>>,
+) -> Result<Vec<u8>, GeneralError> {
+    let mut bytes = vec![];
+
+    loop {
+        match iter.next() {
+            Some((_, '"')) => return Ok(bytes),
+            Some((_, '\\')) => bytes.push(parse_escape(iter)?),
+            Some((_, '\n')) | None => {
+                return Err(GeneralError::new("文字列が閉じられていません".to_string()))
+            }
+            Some((_, c)) => {
+                let mut buf = [0; 4];
+                bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            }
+        }
+    }
 }
@@ -0,0 +1,76 @@
+use crate::dump;
+use crate::parser::Node;
+use crate::source::SourceFile;
+use crate::tokenizer::Token;
+
+/// トレースを有効にする環境変数. 値は `--trace=` と同じ形式
+pub const TRACE_ENV: &str = "RUST_9CC_TRACE";
+
+/// コンパイルの各段階の結果を標準エラー出力に書き出すかどうか
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Trace {
+    tokens: bool,
+    parse: bool,
+    codegen: bool,
+}
+
+impl Trace {
+    /// `tokens,parse,codegen` や `all` のようなカンマ区切りの指定を読む
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut trace = Self::default();
+        for stage in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            match stage {
+                "tokens" => trace.tokens = true,
+                "parse" => trace.parse = true,
+                "codegen" => trace.codegen = true,
+                "all" => {
+                    trace.tokens = true;
+                    trace.parse = true;
+                    trace.codegen = true;
+                }
+                _ => return Err(format!("不明なトレース対象です: {}", stage)),
+            }
+        }
+        Ok(trace)
+    }
+
+    /// `RUST_9CC_TRACE` から読む. 設定されていなければ何もトレースしない
+    pub fn from_env() -> Result<Self, String> {
+        match std::env::var(TRACE_ENV) {
+            Ok(spec) => Self::parse(&spec),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    pub fn merge(self, other: Self) -> Self {
+        Self {
+            tokens: self.tokens || other.tokens,
+            parse: self.parse || other.parse,
+            codegen: self.codegen || other.codegen,
+        }
+    }
+
+    pub fn tokens(&self, file: &SourceFile, tokens: &[Token]) {
+        if !self.tokens {
+            return;
+        }
+        eprintln!("=== tokens: {} ===", file.name());
+        eprint!("{}", dump::tokens(tokens));
+    }
+
+    pub fn parse_result(&self, file: &SourceFile, program: &[Node]) {
+        if !self.parse {
+            return;
+        }
+        eprintln!("=== parse: {} ===", file.name());
+        eprint!("{}", dump::ast(program));
+    }
+
+    pub fn codegen(&self, file: &SourceFile, assembly: &str) {
+        if !self.codegen {
+            return;
+        }
+        eprintln!("=== codegen: {} ===", file.name());
+        eprint!("{}", assembly);
+    }
+}
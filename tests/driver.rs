@@ -0,0 +1,671 @@
+//! ビルドした `rust-9cc` を実際に起動して, コマンドラインの扱いを確かめる.
+//!
+//! 各テストは専用の一時ディレクトリをカレントディレクトリにして実行する.
+#![cfg(target_arch = "x86_64")]
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+const RETURN_42: &str = "int main() { return 42; }\n";
+
+/// テストごとの空の作業ディレクトリ
+fn work_dir(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rust-9cc-driver-{}-{}", std::process::id(), test));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn rust_9cc(dir: &Path) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_rust-9cc"));
+    command.current_dir(dir).env_remove("RUST_9CC_TRACE");
+    command
+}
+
+fn run(command: &mut Command) -> Output {
+    command.output().expect("rust-9cc を実行できません")
+}
+
+/// 標準入力に `input` を流し込んで実行する
+fn run_with_stdin(command: &mut Command, input: &str) -> Output {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("rust-9cc を実行できません");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).to_string()
+}
+
+fn assert_success(output: &Output) {
+    assert!(
+        output.status.success(),
+        "rust-9cc が失敗しました: {}",
+        stderr(output)
+    );
+}
+
+/// 生成したアセンブリを cc でリンクして実行し, 終了コードを返す
+fn exit_code_of_assembly(dir: &Path, assembly: &Path) -> i32 {
+    let executable = dir.join("from-assembly");
+    let status = Command::new("cc")
+        .arg("-o")
+        .arg(&executable)
+        .arg(assembly)
+        .status()
+        .unwrap();
+    assert!(
+        status.success(),
+        "{} をリンクできません",
+        assembly.display()
+    );
+    exit_code(&executable)
+}
+
+fn exit_code(executable: &Path) -> i32 {
+    Command::new(executable)
+        .status()
+        .expect("実行できません")
+        .code()
+        .unwrap()
+}
+
+#[test]
+fn reads_source_file() {
+    let dir = work_dir("reads_source_file");
+    std::fs::write(dir.join("prog.c"), RETURN_42).unwrap();
+
+    let output = run(rust_9cc(&dir).args(["-S", "prog.c", "-o", "-"]));
+    assert_success(&output);
+    assert!(stdout(&output).contains("main:"));
+}
+
+#[test]
+fn reads_stdin() {
+    let dir = work_dir("reads_stdin");
+
+    let output = run_with_stdin(rust_9cc(&dir).args(["-S", "-"]), RETURN_42);
+    assert_success(&output);
+    std::fs::write(dir.join("out.s"), stdout(&output)).unwrap();
+    assert_eq!(exit_code_of_assembly(&dir, &dir.join("out.s")), 42);
+}
+
+#[test]
+fn one_assembly_per_input() {
+    let dir = work_dir("one_assembly_per_input");
+    std::fs::write(dir.join("a.c"), RETURN_42).unwrap();
+    std::fs::write(dir.join("b.c"), "int b() { return 1; }\n").unwrap();
+
+    assert_success(&run(rust_9cc(&dir).args(["-S", "a.c", "b.c"])));
+    let a = std::fs::read_to_string(dir.join("a.s")).unwrap();
+    let b = std::fs::read_to_string(dir.join("b.s")).unwrap();
+    assert!(a.contains("main:") && !a.contains("b:"));
+    assert!(b.contains("b:") && !b.contains("main:"));
+}
+
+#[test]
+fn accepts_crlf_and_tabs() {
+    let dir = work_dir("accepts_crlf_and_tabs");
+    std::fs::write(
+        dir.join("crlf.c"),
+        "int main() {\r\n\tint x;\r\n\tx = 40;\r\n\treturn x + 2;\r\n}\r\n",
+    )
+    .unwrap();
+
+    assert_success(&run(rust_9cc(&dir).args(["-S", "crlf.c"])));
+    assert_eq!(exit_code_of_assembly(&dir, &dir.join("crlf.s")), 42);
+}
+
+#[test]
+fn missing_input_file() {
+    let dir = work_dir("missing_input_file");
+
+    let output = run(rust_9cc(&dir).args(["-S", "nowhere.c"]));
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("nowhere.c: 読み込めません"));
+}
+
+fn other_c() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("clang/other.c")
+}
+
+/// システムのヘッダを使う `clang/other.c` は rust-9cc では読めないので cc でオブジェクトにしておく
+fn other_object(dir: &Path) -> PathBuf {
+    let object = dir.join("other.o");
+    let status = Command::new("cc")
+        .arg("-c")
+        .arg("-o")
+        .arg(&object)
+        .arg(other_c())
+        .status()
+        .unwrap();
+    assert!(status.success(), "clang/other.c をコンパイルできません");
+    object
+}
+
+#[test]
+fn assembly_goes_to_current_directory() {
+    let dir = work_dir("assembly_goes_to_current_directory");
+    std::fs::create_dir(dir.join("src")).unwrap();
+    std::fs::write(dir.join("src/prog.c"), RETURN_42).unwrap();
+
+    assert_success(&run(rust_9cc(&dir).args(["-S", "src/prog.c"])));
+    assert!(dir.join("prog.s").exists());
+    assert!(!dir.join("src/prog.s").exists());
+
+    assert_success(&run(rust_9cc(&dir).args([
+        "-S",
+        "-o",
+        "named.s",
+        "src/prog.c",
+    ])));
+    assert_eq!(exit_code_of_assembly(&dir, &dir.join("named.s")), 42);
+}
+
+#[test]
+fn object_file() {
+    let dir = work_dir("object_file");
+    std::fs::write(dir.join("prog.c"), RETURN_42).unwrap();
+
+    assert_success(&run(rust_9cc(&dir).args(["-c", "prog.c"])));
+    assert_success(&run(rust_9cc(&dir).args(["-c", "prog.c", "-ocustom.o"])));
+    for object in ["prog.o", "custom.o"] {
+        let status = Command::new("cc")
+            .current_dir(&dir)
+            .args(["-o", "prog", object])
+            .status()
+            .unwrap();
+        assert!(status.success(), "{} をリンクできません", object);
+        assert_eq!(exit_code(&dir.join("prog")), 42);
+    }
+}
+
+#[test]
+fn links_executable_with_other_object() {
+    let dir = work_dir("links_executable_with_other_object");
+    std::fs::write(
+        dir.join("prog.c"),
+        "int foo(); int main() { foo(); return 7; }\n",
+    )
+    .unwrap();
+    let other = other_object(&dir);
+
+    assert_success(&run(rust_9cc(&dir).arg("prog.c").arg(&other)));
+    let output = Command::new(dir.join("a.out")).output().unwrap();
+    assert_eq!(output.status.code(), Some(7));
+    assert_eq!(stdout(&output), "Hello World\n");
+
+    assert_success(&run(rust_9cc(&dir)
+        .args(["-o", "hello", "prog.c"])
+        .arg(&other)));
+    assert_eq!(exit_code(&dir.join("hello")), 7);
+}
+
+#[test]
+fn does_not_hand_sources_to_cc() {
+    let dir = work_dir("does_not_hand_sources_to_cc");
+
+    // ヘッダを展開した後のソースも rust-9cc で読むので, 読めなければエラーになる
+    let output = run(rust_9cc(&dir)
+        .arg("-S")
+        .arg("-o")
+        .arg("other.s")
+        .arg(other_c()));
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("error:"));
+    assert!(!dir.join("other.s").exists());
+}
+
+#[test]
+fn links_assembly_and_object_inputs() {
+    let dir = work_dir("links_assembly_and_object_inputs");
+    std::fs::write(
+        dir.join("main.c"),
+        "int two(); int main() { return two() + 40; }\n",
+    )
+    .unwrap();
+    std::fs::write(dir.join("two.c"), "int two() { return 2; }\n").unwrap();
+
+    assert_success(&run(rust_9cc(&dir).args(["-S", "main.c"])));
+    assert_success(&run(rust_9cc(&dir).args(["-c", "two.c"])));
+    assert_success(&run(rust_9cc(&dir).args(["-o", "prog", "main.s", "two.o"])));
+    assert_eq!(exit_code(&dir.join("prog")), 42);
+}
+
+#[test]
+fn preprocessed_source_in_assembly_mode() {
+    let dir = work_dir("preprocessed_source_in_assembly_mode");
+    std::fs::write(
+        dir.join("inc.c"),
+        "#define ANSWER 42\nint main() { return ANSWER; }\n",
+    )
+    .unwrap();
+
+    assert_success(&run(rust_9cc(&dir).args(["-S", "inc.c"])));
+    let assembly = std::fs::read_to_string(dir.join("inc.s")).unwrap();
+    assert!(assembly.starts_with(".intel_syntax noprefix\n"));
+    assert_eq!(exit_code_of_assembly(&dir, &dir.join("inc.s")), 42);
+
+    let output = run(rust_9cc(&dir).args(["--emit=tokens", "inc.c"]));
+    assert_success(&output);
+    assert!(stdout(&output).contains("2:21\tnum\t42\n"));
+}
+
+#[test]
+fn preprocessed_source_keeps_line_numbers() {
+    let dir = work_dir("preprocessed_source_keeps_line_numbers");
+    std::fs::write(
+        dir.join("answer.h"),
+        "#define ANSWER 42\nint answer(void);\n",
+    )
+    .unwrap();
+    let source =
+        "#include \"answer.h\"\n\n#define BROKEN +;\nint main() {\n  return ANSWER BROKEN\n}\n";
+    std::fs::write(dir.join("inc.c"), source).unwrap();
+
+    let output = run(rust_9cc(&dir).args(["-S", "inc.c"]));
+    assert!(!output.status.success());
+    assert!(
+        stderr(&output).starts_with("inc.c:5:"),
+        "{}",
+        stderr(&output)
+    );
+
+    // 標準入力から読んだソースのヘッダもカレントディレクトリから探す
+    let output = run_with_stdin(rust_9cc(&dir).args(["-S", "-"]), source);
+    assert!(
+        stderr(&output).starts_with("<stdin>:5:"),
+        "{}",
+        stderr(&output)
+    );
+
+    let fixed = source.replace("+;", ";");
+    let output = run_with_stdin(rust_9cc(&dir).args(["-o", "prog", "-"]), &fixed);
+    assert_success(&output);
+    assert_eq!(exit_code(&dir.join("prog")), 42);
+}
+
+#[test]
+fn rejects_bad_arguments() {
+    let dir = work_dir("rejects_bad_arguments");
+    std::fs::write(dir.join("a.c"), RETURN_42).unwrap();
+    std::fs::write(dir.join("b.c"), RETURN_42).unwrap();
+
+    for (args, message) in [
+        (
+            &["-S", "-o", "x.s", "a.c", "b.c"][..],
+            "-o は指定できません",
+        ),
+        (&["-x", "a.c"][..], "不明なオプションです: -x"),
+        (&["-S"][..], "入力ファイルがありません"),
+        (&["a.c", "-o"][..], "-o の後に出力ファイル名が必要です"),
+    ] {
+        let output = run(rust_9cc(&dir).args(args));
+        assert!(!output.status.success(), "{:?} が成功しました", args);
+        assert!(
+            stderr(&output).contains(message),
+            "{:?}: {}",
+            args,
+            stderr(&output)
+        );
+    }
+}
+
+#[test]
+fn reports_diagnostic_with_caret() {
+    let dir = work_dir("reports_diagnostic_with_caret");
+    std::fs::write(dir.join("bad.c"), "int main() {\n  return 1 @ 2;\n}\n").unwrap();
+
+    let output = run(rust_9cc(&dir).args(["-S", "bad.c"]));
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stderr(&output),
+        "bad.c:2:12: error: トークナイズ出来ません\n  return 1 @ 2;\n           ^\n"
+    );
+    assert!(!dir.join("bad.s").exists());
+}
+
+#[test]
+fn reports_parse_error_from_stdin() {
+    let dir = work_dir("reports_parse_error_from_stdin");
+
+    let output = run_with_stdin(
+        rust_9cc(&dir).args(["-S", "-"]),
+        "int main() { return 1 }\n",
+    );
+    assert_eq!(output.status.code(), Some(1));
+    let message = stderr(&output);
+    assert!(message.starts_with("<stdin>:1:23: error: ';' が必要ですが '}' があります\n"));
+    assert!(message.ends_with("int main() { return 1 }\n                      ^\n"));
+    assert!(!message.contains("panicked"));
+}
+
+#[test]
+fn silent_by_default() {
+    let dir = work_dir("silent_by_default");
+    std::fs::write(dir.join("prog.c"), RETURN_42).unwrap();
+
+    let output = run(rust_9cc(&dir).args(["-S", "prog.c"]));
+    assert_success(&output);
+    assert_eq!(stderr(&output), "");
+    assert_eq!(stdout(&output), "");
+}
+
+#[test]
+fn trace_option() {
+    let dir = work_dir("trace_option");
+    std::fs::write(dir.join("prog.c"), RETURN_42).unwrap();
+
+    let output = run(rust_9cc(&dir).args(["-S", "-o", "-", "--trace=tokens", "prog.c"]));
+    assert_success(&output);
+    let trace = stderr(&output);
+    assert!(trace.starts_with("=== tokens: prog.c ===\n1:1\tkeyword\tint\n"));
+    assert!(!trace.contains("=== parse"));
+    assert!(!trace.contains("=== codegen"));
+    // トレースは標準エラー出力だけに出る
+    assert!(!stdout(&output).contains("==="));
+
+    let output = run(rust_9cc(&dir).args(["-S", "--trace=all", "prog.c"]));
+    let trace = stderr(&output);
+    for header in ["tokens", "parse", "codegen"] {
+        assert!(trace.contains(&format!("=== {}: prog.c ===\n", header)));
+    }
+    assert!(trace.contains("DefineFunction name=main"));
+    assert!(trace.contains("main:\n  push rbp\n"));
+}
+
+#[test]
+fn trace_environment_variable() {
+    let dir = work_dir("trace_environment_variable");
+    std::fs::write(dir.join("prog.c"), RETURN_42).unwrap();
+
+    let output = run(rust_9cc(&dir)
+        .env("RUST_9CC_TRACE", "parse")
+        .args(["-S", "prog.c"]));
+    assert_success(&output);
+    assert!(stderr(&output).starts_with("=== parse: prog.c ===\nDefineFunction"));
+
+    let output = run(rust_9cc(&dir)
+        .env("RUST_9CC_TRACE", "parse,lexer")
+        .args(["-S", "prog.c"]));
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("不明なトレース対象です: lexer"));
+}
+
+/// `--emit=ast-json` の出力を確かめるための最小限の JSON の値
+#[derive(Debug, PartialEq)]
+enum Json {
+    Null,
+    Bool(bool),
+    Num(f64),
+    Str(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    fn parse(text: &str) -> Result<Json, String> {
+        let mut chars = text.trim_end().chars().peekable();
+        let value = Self::value(&mut chars)?;
+        match chars.next() {
+            None => Ok(value),
+            Some(c) => Err(format!("値の後に {:?} があります", c)),
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn value(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<Json, String> {
+        match chars.next() {
+            Some('n') => Self::literal(chars, "ull", Json::Null),
+            Some('t') => Self::literal(chars, "rue", Json::Bool(true)),
+            Some('f') => Self::literal(chars, "alse", Json::Bool(false)),
+            Some('"') => Self::string(chars).map(Json::Str),
+            Some('[') => {
+                let mut elements = vec![];
+                if chars.next_if_eq(&']').is_none() {
+                    loop {
+                        elements.push(Self::value(chars)?);
+                        match chars.next() {
+                            Some(',') => continue,
+                            Some(']') => break,
+                            c => return Err(format!("配列の中に {:?} があります", c)),
+                        }
+                    }
+                }
+                Ok(Json::Array(elements))
+            }
+            Some('{') => {
+                let mut members = vec![];
+                if chars.next_if_eq(&'}').is_none() {
+                    loop {
+                        if chars.next() != Some('"') {
+                            return Err("オブジェクトのキーが文字列ではありません".to_string());
+                        }
+                        let key = Self::string(chars)?;
+                        if chars.next() != Some(':') {
+                            return Err(format!("キー {} の後に ':' がありません", key));
+                        }
+                        members.push((key, Self::value(chars)?));
+                        match chars.next() {
+                            Some(',') => continue,
+                            Some('}') => break,
+                            c => return Err(format!("オブジェクトの中に {:?} があります", c)),
+                        }
+                    }
+                }
+                Ok(Json::Object(members))
+            }
+            Some(c) if c == '-' || c.is_ascii_digit() => {
+                let mut number = c.to_string();
+                while let Some(c) = chars.next_if(|c| c.is_ascii_digit() || ".eE+-".contains(*c)) {
+                    number.push(c);
+                }
+                number
+                    .parse()
+                    .map(Json::Num)
+                    .map_err(|_| format!("数として読めません: {}", number))
+            }
+            c => Err(format!("値が必要ですが {:?} があります", c)),
+        }
+    }
+
+    fn literal(
+        chars: &mut std::iter::Peekable<std::str::Chars>,
+        rest: &str,
+        value: Json,
+    ) -> Result<Json, String> {
+        for expected in rest.chars() {
+            if chars.next() != Some(expected) {
+                return Err(format!("不正なリテラルです (..{})", rest));
+            }
+        }
+        Ok(value)
+    }
+
+    /// 開きの `"` を読んだ後から閉じの `"` までを読む
+    fn string(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<String, String> {
+        let mut s = String::new();
+        loop {
+            match chars.next() {
+                Some('"') => return Ok(s),
+                Some('\\') => match chars.next() {
+                    Some('"') => s.push('"'),
+                    Some('\\') => s.push('\\'),
+                    Some('/') => s.push('/'),
+                    Some('n') => s.push('\n'),
+                    Some('r') => s.push('\r'),
+                    Some('t') => s.push('\t'),
+                    Some('b') => s.push('\u{8}'),
+                    Some('f') => s.push('\u{c}'),
+                    Some('u') => {
+                        let hex: String = chars.by_ref().take(4).collect();
+                        let code = u32::from_str_radix(&hex, 16)
+                            .map_err(|_| format!("不正な \\u エスケープです: {}", hex))?;
+                        s.push(char::from_u32(code).ok_or("サロゲートは扱いません")?);
+                    }
+                    c => return Err(format!("不正なエスケープです: {:?}", c)),
+                },
+                Some(c) if (c as u32) < 0x20 => {
+                    return Err(format!("文字列に制御文字 {:?} がそのまま入っています", c))
+                }
+                Some(c) => s.push(c),
+                None => return Err("文字列が閉じられていません".to_string()),
+            }
+        }
+    }
+}
+
+#[test]
+fn emit_tokens() {
+    let dir = work_dir("emit_tokens");
+    std::fs::write(dir.join("prog.c"), "int main() {\n  return 0x2a;\n}\n").unwrap();
+
+    let output = run(rust_9cc(&dir).args(["--emit=tokens", "prog.c"]));
+    assert_success(&output);
+    assert_eq!(
+        stdout(&output),
+        "1:1\tkeyword\tint\n\
+         1:5\tident\tmain\n\
+         1:9\treserved\t(\n\
+         1:10\treserved\t)\n\
+         1:12\treserved\t{\n\
+         2:3\tkeyword\treturn\n\
+         2:10\tnum\t42\n\
+         2:14\treserved\t;\n\
+         3:1\treserved\t}\n\
+         4:1\teof\tEOF\n"
+    );
+}
+
+#[test]
+fn emit_ast() {
+    let dir = work_dir("emit_ast");
+    std::fs::write(dir.join("prog.c"), "int main() {\n  return 1 + 2;\n}\n").unwrap();
+
+    let output = run(rust_9cc(&dir).args(["--emit=ast", "prog.c"]));
+    assert_success(&output);
+    assert_eq!(
+        stdout(&output),
+        "DefineFunction name=main return_type=int @1:1\n\
+        \x20 params:\n\
+        \x20 statements:\n\
+        \x20   Return @2:3\n\
+        \x20     value: Operator2 op=Add @2:12\n\
+        \x20       left: Num value=1 type=int @2:10\n\
+        \x20       right: Num value=2 type=int @2:14\n"
+    );
+}
+
+#[test]
+fn emit_ast_unsigned_numbers() {
+    let dir = work_dir("emit_ast_unsigned_numbers");
+    std::fs::write(
+        dir.join("prog.c"),
+        "int main() {\n  return 4294967295u + 18446744073709551615u;\n}\n",
+    )
+    .unwrap();
+
+    let output = run(rust_9cc(&dir).args(["--emit=ast", "prog.c"]));
+    assert_success(&output);
+    let text = stdout(&output);
+    assert!(
+        text.contains("Num value=4294967295 type=unsigned int @2:10"),
+        "{}",
+        text
+    );
+    assert!(
+        text.contains("Num value=18446744073709551615 type=unsigned long @2:24"),
+        "{}",
+        text
+    );
+
+    let output = run(rust_9cc(&dir).args(["--emit=ast-json", "prog.c"]));
+    assert_success(&output);
+    assert!(
+        stdout(&output).contains("\"value\":18446744073709551615,"),
+        "{}",
+        stdout(&output)
+    );
+}
+
+#[test]
+fn emit_ast_json() {
+    let dir = work_dir("emit_ast_json");
+    std::fs::write(
+        dir.join("prog.c"),
+        "int main() {\n  char *s = \"q\\\"\tb\\\\\";\n  return -1;\n}\n",
+    )
+    .unwrap();
+
+    let output = run(rust_9cc(&dir).args(["--emit=ast-json", "prog.c"]));
+    assert_success(&output);
+    let json = Json::parse(&stdout(&output))
+        .unwrap_or_else(|e| panic!("JSON として読めません: {}\n{}", e, stdout(&output)));
+
+    let Json::Array(program) = &json else {
+        panic!("最上位が配列ではありません: {:?}", json);
+    };
+    let main = &program[0];
+    assert_eq!(
+        main.get("kind"),
+        Some(&Json::Str("DefineFunction".to_string()))
+    );
+    assert_eq!(main.get("name"), Some(&Json::Str("main".to_string())));
+    let span = main.get("span").unwrap();
+    assert_eq!(span.get("line"), Some(&Json::Num(1.0)));
+    assert_eq!(span.get("column"), Some(&Json::Num(1.0)));
+
+    // 文字列リテラルは C のエスケープ表記で出力され, その引用符とバックスラッシュが
+    // さらに JSON としてエスケープされる
+    let Some(Json::Array(statements)) = main.get("statements") else {
+        panic!("statements がありません: {:?}", main);
+    };
+    let Some(Json::Array(block)) = statements[0].get("statements") else {
+        panic!("初期化のブロックがありません: {:?}", statements[0]);
+    };
+    let literal = block[1].get("right").unwrap();
+    assert_eq!(
+        literal.get("kind"),
+        Some(&Json::Str("StringLiteral".to_string()))
+    );
+    assert_eq!(
+        literal.get("value"),
+        Some(&Json::Str("q\\\"\\tb\\\\".to_string()))
+    );
+}
+
+#[test]
+fn emit_asm_to_file() {
+    let dir = work_dir("emit_asm_to_file");
+    std::fs::write(dir.join("prog.c"), RETURN_42).unwrap();
+
+    let output = run(rust_9cc(&dir).args(["--emit=asm", "-o", "out.s", "prog.c"]));
+    assert_success(&output);
+    assert_eq!(stdout(&output), "");
+    assert_eq!(exit_code_of_assembly(&dir, &dir.join("out.s")), 42);
+
+    let output = run(rust_9cc(&dir).args(["--emit=llvm", "prog.c"]));
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("不明な --emit の指定です: llvm"));
+}
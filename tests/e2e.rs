@@ -0,0 +1,310 @@
+//! `tests/cases/*.txt` に書いた C プログラムをコンパイル, リンク, 実行して結果を確かめる.
+//!
+//! ケースファイルの形式:
+//!
+//! ```text
+//! # 最初の `===` より前の行はコメント
+//! === 3
+//! int main() { return 1 + 2; }
+//!
+//! === 0
+//! int main() { foo(); return 0; }
+//! --- stdout
+//! Hello World
+//!
+//! === error
+//! int main() { return x; }
+//! --- error
+//! 1:21: error: 変数 'x' は定義されていません
+//!
+//! === 0
+//! int main() { return f(); }
+//! --- warning
+//! 関数 'f' は宣言されていません
+//! ```
+//!
+//! `=== <終了コード>` の後にプログラムを書く. `--- stdout` を続けると標準出力も比較する.
+//! `=== error` はコンパイルエラーになることを期待し, `--- error` の各行が診断メッセージに
+//! 含まれるかを確かめる. `--- warning` の各行は成功したときの警告に含まれるかを確かめる. 生成したアセンブリは `clang/other.c` と一緒にリンクする.
+#![cfg(target_arch = "x86_64")]
+
+use rust_9cc::source::SourceFile;
+use rust_9cc::{compile_with_warnings, CompileOptions};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+
+#[derive(Debug)]
+enum Expect {
+    Exit(i32),
+    Error,
+}
+
+#[derive(Debug)]
+struct Case {
+    /// `ファイル名:行番号` (`===` の行)
+    name: String,
+    expect: Expect,
+    source: String,
+    stdout: Option<String>,
+    errors: Vec<String>,
+    warnings: Vec<String>,
+}
+
+fn manifest_dir() -> &'static Path {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+}
+
+fn work_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rust-9cc-e2e-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// `clang/other.c` をテストプロセスにつき1回だけコンパイルする
+fn other_object() -> &'static Path {
+    static OTHER: OnceLock<PathBuf> = OnceLock::new();
+    OTHER.get_or_init(|| {
+        let object = work_dir().join("other.o");
+        let status = Command::new("cc")
+            .arg("-c")
+            .arg("-o")
+            .arg(&object)
+            .arg(manifest_dir().join("clang/other.c"))
+            .status()
+            .expect("cc を実行できません");
+        assert!(status.success(), "clang/other.c をコンパイルできません");
+        object
+    })
+}
+
+fn trim_trailing_blank_lines(lines: &[&str]) -> String {
+    let end = lines
+        .iter()
+        .rposition(|line| !line.trim().is_empty())
+        .map_or(0, |i| i + 1);
+    lines[..end].join("\n")
+}
+
+fn non_blank_lines(lines: &[&str]) -> Vec<String> {
+    lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.to_string())
+        .collect()
+}
+
+fn parse_cases(file_name: &str, contents: &str) -> Vec<Case> {
+    let mut cases = vec![];
+    let lines: Vec<&str> = contents.lines().collect();
+    let mut i = lines
+        .iter()
+        .position(|line| line.starts_with("==="))
+        .unwrap_or(lines.len());
+
+    while i < lines.len() {
+        let header = lines[i].trim_start_matches("===").trim();
+        let name = format!("{}:{}", file_name, i + 1);
+        let expect = match header {
+            "error" => Expect::Error,
+            code => Expect::Exit(
+                code.parse()
+                    .unwrap_or_else(|_| panic!("{}: 終了コードが読めません: {}", name, code)),
+            ),
+        };
+        i += 1;
+
+        let end = lines[i..]
+            .iter()
+            .position(|line| line.starts_with("==="))
+            .map_or(lines.len(), |n| i + n);
+        let body = &lines[i..end];
+        let source_end = body
+            .iter()
+            .position(|line| line.starts_with("---"))
+            .unwrap_or(body.len());
+        let source = trim_trailing_blank_lines(&body[..source_end]);
+
+        let mut stdout = None;
+        let mut errors = vec![];
+        let mut warnings = vec![];
+        let mut j = source_end;
+        while j < body.len() {
+            let section = body[j].trim_start_matches("---").trim();
+            let section_end = body[j + 1..]
+                .iter()
+                .position(|line| line.starts_with("---"))
+                .map_or(body.len(), |n| j + 1 + n);
+            let section_lines = &body[j + 1..section_end];
+            match section {
+                "stdout" => stdout = Some(trim_trailing_blank_lines(section_lines)),
+                "error" => errors.extend(non_blank_lines(section_lines)),
+                "warning" => warnings.extend(non_blank_lines(section_lines)),
+                _ => panic!("{}: 不明なセクションです: {}", name, section),
+            }
+            j = section_end;
+        }
+
+        cases.push(Case {
+            name,
+            expect,
+            source,
+            stdout,
+            errors,
+            warnings,
+        });
+        i = end;
+    }
+
+    cases
+}
+
+/// ケースを1つ実行し, 期待と違えばその説明を返す
+fn run_case(case: &Case, index: usize, stem: &str) -> Result<(), String> {
+    let source = SourceFile::new(0, case.name.clone(), case.source.clone());
+    let result = compile_with_warnings(&source, &CompileOptions::new());
+
+    match (&case.expect, result) {
+        (Expect::Error, Ok(_)) => Err("コンパイルエラーになるはずが成功しました".to_string()),
+        (Expect::Error, Err(diagnostics)) => {
+            let rendered = diagnostics.render(&source);
+            match case.errors.iter().find(|e| !rendered.contains(e.as_str())) {
+                Some(missing) => Err(format!(
+                    "診断メッセージに {:?} が含まれません:\n{}",
+                    missing, rendered
+                )),
+                None => Ok(()),
+            }
+        }
+        (Expect::Exit(_), Err(diagnostics)) => Err(format!(
+            "コンパイルできません:\n{}",
+            diagnostics.render(&source)
+        )),
+        (Expect::Exit(code), Ok((assembly, warnings))) => {
+            let rendered = warnings.render(&source);
+            if let Some(missing) = case
+                .warnings
+                .iter()
+                .find(|w| !rendered.contains(w.as_str()))
+            {
+                return Err(format!(
+                    "警告に {:?} が含まれません:\n{}",
+                    missing, rendered
+                ));
+            }
+            let executable = work_dir().join(format!("{}-{}", stem, index));
+            let stdout = link_and_run(&assembly, &executable, *code)?;
+            match &case.stdout {
+                Some(expected) if stdout.trim_end_matches('\n') != expected => Err(format!(
+                    "stdout: {:?} expected, but got {:?}",
+                    expected, stdout
+                )),
+                _ => Ok(()),
+            }
+        }
+    }
+}
+
+/// アセンブリを `clang/other.c` とリンクして実行し, 終了コードを確かめて標準出力を返す
+fn link_and_run(assembly: &str, executable: &Path, expected_code: i32) -> Result<String, String> {
+    let assembly_path = executable.with_extension("s");
+    std::fs::write(&assembly_path, assembly).unwrap();
+
+    let link = Command::new("cc")
+        .arg("-o")
+        .arg(executable)
+        .arg(&assembly_path)
+        .arg(other_object())
+        .output()
+        .expect("cc を実行できません");
+    if !link.status.success() {
+        return Err(format!(
+            "リンクできません:\n{}",
+            String::from_utf8_lossy(&link.stderr)
+        ));
+    }
+
+    let output = Command::new(executable).output().expect("実行できません");
+    let _ = std::fs::remove_file(&assembly_path);
+    let _ = std::fs::remove_file(executable);
+
+    let actual = output.status.code();
+    if actual != Some(expected_code) {
+        return Err(format!("{} expected, but got {:?}", expected_code, actual));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn run_cases(file_name: &str) {
+    let path = manifest_dir().join("tests/cases").join(file_name);
+    let contents = std::fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("{} を読み込めません: {}", path.display(), e));
+    let stem = file_name.trim_end_matches(".txt");
+
+    let cases = parse_cases(file_name, &contents);
+    assert!(!cases.is_empty(), "{} にケースがありません", file_name);
+
+    let failures: Vec<String> = cases
+        .iter()
+        .enumerate()
+        .filter_map(|(index, case)| {
+            run_case(case, index, stem)
+                .err()
+                .map(|e| format!("{}\n{}\n=> {}", case.name, case.source, e))
+        })
+        .collect();
+
+    if !failures.is_empty() {
+        panic!(
+            "{} / {} 件のケースが失敗しました\n\n{}",
+            failures.len(),
+            cases.len(),
+            failures.join("\n\n")
+        );
+    }
+}
+
+#[test]
+fn expressions() {
+    run_cases("expressions.txt");
+}
+
+#[test]
+fn control() {
+    run_cases("control.txt");
+}
+
+#[test]
+fn variables() {
+    run_cases("variables.txt");
+}
+
+#[test]
+fn functions() {
+    run_cases("functions.txt");
+}
+
+#[test]
+fn errors() {
+    run_cases("errors.txt");
+}
+
+#[test]
+fn literals() {
+    run_cases("literals.txt");
+}
+
+#[test]
+fn types() {
+    run_cases("types.txt");
+}
+
+#[test]
+fn structs() {
+    run_cases("structs.txt");
+}
+
+#[test]
+fn enums() {
+    run_cases("enums.txt");
+}
@@ -0,0 +1,171 @@
+//! ライブラリとして公開している API を直接呼び出して確かめる.
+
+use rust_9cc::diagnostic::Severity;
+use rust_9cc::parser::NodeKind;
+use rust_9cc::source::{SourceFile, Span};
+use rust_9cc::tokenizer::TokenKind;
+use rust_9cc::{
+    compile, generate, generate_to, parse, parse_with_warnings, tokenize, CompileOptions, Emit,
+};
+use std::io::Write;
+
+const FILE_ID: usize = 3;
+
+fn source(contents: &str) -> SourceFile {
+    SourceFile::new(FILE_ID, "span.c".to_string(), contents.to_string())
+}
+
+#[test]
+fn tokens_have_spans() {
+    let file = source("int main() {\n  return 42;\n}\n");
+    let tokens = tokenize(&file).unwrap();
+
+    let spans: Vec<(usize, usize, usize)> = tokens
+        .iter()
+        .map(|token| {
+            let span = token.span();
+            assert_eq!(span.file_id(), FILE_ID);
+            (span.line(), span.column(), span.offset())
+        })
+        .collect();
+    assert_eq!(
+        spans,
+        [
+            (1, 1, 0),
+            (1, 5, 4),
+            (1, 9, 8),
+            (1, 10, 9),
+            (1, 12, 11),
+            (2, 3, 15),
+            (2, 10, 22),
+            (2, 12, 24),
+            (3, 1, 26),
+            (4, 1, 28),
+        ]
+    );
+    assert!(matches!(tokens[6].kind(), TokenKind::Num(42, _)));
+    assert!(matches!(tokens.last().unwrap().kind(), TokenKind::Eof));
+}
+
+#[test]
+fn nodes_have_spans() {
+    let file = source("int main() {\n  return 1 +\n    2;\n}\n");
+    let program = parse(tokenize(&file).unwrap()).unwrap();
+
+    assert_eq!(program[0].span(), Span::new(FILE_ID, 0, 1, 1));
+    let NodeKind::DefineFunction(function) = program[0].kind() else {
+        panic!("関数定義ではありません: {:?}", program[0]);
+    };
+    let statement = &function.statements()[0];
+    assert_eq!(statement.span(), Span::new(FILE_ID, 15, 2, 3));
+    let NodeKind::Return(Some(value)) = statement.kind() else {
+        panic!("return 文ではありません: {:?}", statement);
+    };
+    // 二項演算子のノードは演算子の位置を指す
+    assert_eq!(value.span(), Span::new(FILE_ID, 24, 2, 12));
+}
+
+#[test]
+fn errors_have_spans() {
+    let file = source("int main() {\n  return 1 @ 2;\n}\n");
+    let error = tokenize(&file).unwrap_err();
+    assert_eq!(error.span(), Span::new(FILE_ID, 24, 2, 12));
+
+    let file = source("int main() {\n  return x;\n}\n");
+    let error = parse(tokenize(&file).unwrap()).unwrap_err();
+    assert_eq!(error.span(), Some(Span::new(FILE_ID, 22, 2, 10)));
+}
+
+#[test]
+fn compile_returns_assembly() {
+    let file = source("int main() { return 42; }");
+    let assembly = compile(&file, &CompileOptions::new()).unwrap();
+    assert!(assembly.starts_with(".intel_syntax noprefix\n"));
+    assert!(assembly.contains("main:\n"));
+
+    // 各段階を個別に呼んでも同じアセンブリになる
+    let program = parse(tokenize(&file).unwrap()).unwrap();
+    assert_eq!(generate(&program).unwrap(), assembly);
+}
+
+#[test]
+fn compile_stops_at_emit_stage() {
+    let file = source("int main() { return 42; }");
+    let tokens = compile(&file, &CompileOptions::new().with_emit(Emit::Tokens)).unwrap();
+    assert!(tokens.starts_with("1:1\tkeyword\tint\n"));
+    let ast = compile(&file, &CompileOptions::new().with_emit(Emit::Ast)).unwrap();
+    assert!(ast.starts_with("DefineFunction name=main"));
+}
+
+#[test]
+fn compile_reports_diagnostics() {
+    let file = source("int main() {\n  return x;\n}\n");
+    let diagnostics = compile(&file, &CompileOptions::new()).unwrap_err();
+    assert_eq!(diagnostics.len(), 1);
+    let diagnostic = diagnostics.iter().next().unwrap();
+    assert_eq!(diagnostic.message(), "変数 'x' は定義されていません");
+    assert_eq!(
+        diagnostics.render(&file),
+        "span.c:2:10: error: 変数 'x' は定義されていません\n  return x;\n         ^\n"
+    );
+}
+
+#[test]
+fn caret_accounts_for_wide_characters() {
+    let file = source("int main() {\n\tchar *s = \"日本語\"; return x;\n}\n");
+    let diagnostics = compile(&file, &CompileOptions::new()).unwrap_err();
+    assert_eq!(
+        diagnostics.render(&file),
+        "span.c:2:26: error: 変数 'x' は定義されていません\n\
+         \tchar *s = \"日本語\"; return x;\n\
+         \t                           ^\n"
+    );
+}
+
+#[test]
+fn parse_returns_warnings() {
+    let file = source("int main() {\n  int *p = 0;\n  char *q = p;\n  return 0;\n}\n");
+    let tokens = tokenize(&file).unwrap();
+    let (program, warnings) = parse_with_warnings(tokens).unwrap();
+    assert_eq!(program.len(), 1);
+    assert_eq!(warnings.len(), 1);
+    let warning = warnings.iter().next().unwrap();
+    assert_eq!(warning.severity(), Severity::Warning);
+    assert_eq!(warning.span().map(|s| s.line()), Some(3));
+
+    let tokens = tokenize(&source("int main() { int *p = 0; char *q = p; return x; }")).unwrap();
+    let diagnostics = parse_with_warnings(tokens).unwrap_err();
+    let severities: Vec<_> = diagnostics.iter().map(|d| d.severity()).collect();
+    assert_eq!(severities, [Severity::Warning, Severity::Error]);
+}
+
+/// 書き込みが常に失敗する出力先
+struct BrokenWriter;
+
+impl Write for BrokenWriter {
+    fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+        Err(std::io::Error::other("壊れています"))
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn generate_to_any_writer() {
+    let file = source("int main() { return 42; }");
+    let program = parse(tokenize(&file).unwrap()).unwrap();
+
+    let buffer = generate_to(&program, Vec::new()).unwrap();
+    assert_eq!(
+        String::from_utf8(buffer).unwrap(),
+        generate(&program).unwrap()
+    );
+
+    // 同じプロセスで何度生成しても同じ結果になる
+    assert_eq!(generate(&program).unwrap(), generate(&program).unwrap());
+
+    let error = generate_to(&program, BrokenWriter).err().unwrap();
+    assert_eq!(error.message(), "アセンブリを出力できません: 壊れています");
+    assert_eq!(error.span(), None);
+}